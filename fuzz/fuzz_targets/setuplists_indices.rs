@@ -0,0 +1,40 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use seq_phragmen::{setuplists, Assignment};
+
+fuzz_target!(|votes: Vec<(String, u16, Vec<String>)>| {
+    let votelist: Vec<(String, f64, Vec<String>)> = votes
+        .into_iter()
+        .map(|(voterid, budget, approvals)| (voterid, budget as f64, approvals))
+        .collect();
+    let numedges: usize = votelist.iter().map(|v| v.2.len()).sum();
+
+    let (voterlist, candidates) = setuplists(votelist.clone());
+    let a = Assignment::new(voterlist, candidates, None);
+
+    assert_eq!(a.edges().len(), numedges);
+    assert_eq!(a.voters().len(), votelist.len());
+    let mut nextedge = 0;
+    for voter in a.voters() {
+        for edge in &voter.edges {
+            assert_eq!(edge.index(), nextedge);
+            assert_eq!(edge.voterindex(), voter.index);
+            assert!(edge.voterindex() < a.voters().len());
+            assert!(edge.canindex() < a.candidates().len());
+            nextedge += 1;
+        }
+    }
+    for (index, candidate) in a.candidates().iter().enumerate() {
+        assert_eq!(candidate.index, index);
+    }
+
+    let mut approval = vec![0.0; a.candidates().len()];
+    for (_, budget, approvals) in &votelist {
+        for canid in approvals {
+            let candidate = a.candidate_by_id(canid).expect("every approved id is a candidate");
+            approval[candidate.index] += budget;
+        }
+    }
+    assert_eq!(approval.as_slice(), a.approvals());
+});
@@ -0,0 +1,7263 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// Values at or below this are treated as zero when deciding whether an
+/// approval, voter load or candidate support can be divided by. It is an
+/// absolute threshold, chosen well above accumulated `f64` round-off for
+/// budgets of ordinary magnitude. Loads scale like `1 / approval`, so
+/// elections with very large budgets (approvals beyond roughly `1e11`)
+/// should pass a smaller `eps` through `PhragmenOptions`.
+pub const EPS: f64 = 1e-12;
+
+/// Ballots as `(voterid, budget, approved candidate ids)`.
+pub type VoteList = Vec<(String, f64, Vec<String>)>;
+
+/// Ballots as `(voterid, budget, graded approvals)`, see `seq_phragmen_graded`.
+pub type GradedVoteList = Vec<(String, f64, Vec<(String, Grade)>)>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PhragmenError {
+    /// `index` is the ballot's position in the input.
+    InvalidBudget {
+        index: usize,
+        voterid: String,
+        budget: f64,
+    },
+    /// `index` is the ballot's position in the input.
+    DuplicateApproval {
+        index: usize,
+        voterid: String,
+        canid: String,
+    },
+    ApprovalWithoutVoter {
+        canid: String,
+    },
+    Parse {
+        line: usize,
+        message: String,
+    },
+    GroupMinimumsExceedSeats {
+        required: usize,
+        seats: usize,
+    },
+    GroupMinimumUnreachable {
+        group: String,
+        required: usize,
+        available: usize,
+    },
+    TooManyVoters {
+        limit: usize,
+        voters: usize,
+    },
+    TooManyCandidates {
+        limit: usize,
+    },
+    TooManyEdges {
+        limit: usize,
+        edges: usize,
+    },
+}
+
+impl fmt::Display for PhragmenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PhragmenError::InvalidBudget {
+                index,
+                voterid,
+                budget,
+            } => {
+                write!(
+                    f,
+                    "ballot {index}: voter {voterid} has invalid budget {budget}"
+                )
+            }
+            PhragmenError::DuplicateApproval {
+                index,
+                voterid,
+                canid,
+            } => {
+                write!(
+                    f,
+                    "ballot {index}: voter {voterid} approves {canid} more than once"
+                )
+            }
+            PhragmenError::ApprovalWithoutVoter { canid } => {
+                write!(f, "approval of {canid} given before any voter")
+            }
+            PhragmenError::Parse { line, message } => write!(f, "line {line}: {message}"),
+            PhragmenError::GroupMinimumsExceedSeats { required, seats } => {
+                write!(
+                    f,
+                    "group minimums need {required} seats but only {seats} are elected"
+                )
+            }
+            PhragmenError::GroupMinimumUnreachable {
+                group,
+                required,
+                available,
+            } => write!(
+                f,
+                "group {group} needs {required} seats but has only {available} candidates"
+            ),
+            PhragmenError::TooManyVoters { limit, voters } => {
+                write!(f, "{voters} voters exceed the limit of {limit}")
+            }
+            PhragmenError::TooManyCandidates { limit } => {
+                write!(f, "more than {limit} candidates")
+            }
+            PhragmenError::TooManyEdges { limit, edges } => {
+                write!(f, "{edges} approvals exceed the limit of {limit}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PhragmenError {}
+
+/// Rejects elections whose voters or approvals would not fit `Edge`'s
+/// `u32` indices, see `MAX_INDEX`.
+fn check_index_limits(numvoters: usize, numedges: usize) -> Result<(), PhragmenError> {
+    if numvoters > MAX_INDEX {
+        return Err(PhragmenError::TooManyVoters {
+            limit: MAX_INDEX,
+            voters: numvoters,
+        });
+    }
+    if numedges > MAX_INDEX {
+        return Err(PhragmenError::TooManyEdges {
+            limit: MAX_INDEX,
+            edges: numedges,
+        });
+    }
+    Ok(())
+}
+
+fn check_budget(index: usize, voterid: &str, budget: f64) -> Result<(), PhragmenError> {
+    if budget.is_finite() && budget > 0.0 {
+        Ok(())
+    } else {
+        Err(PhragmenError::InvalidBudget {
+            index,
+            voterid: voterid.to_string(),
+            budget,
+        })
+    }
+}
+
+/// A validated `VoteList`. Every budget is finite and positive.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct VoteSet(pub VoteList);
+
+impl VoteSet {
+    pub fn into_inner(self) -> VoteList {
+        self.0
+    }
+}
+
+/// One voter's ballot: who casts it, the stake behind it, and the
+/// candidates it approves. The functions taking ballots by value accept
+/// anything convertible into a `Ballot`, so the `(voterid, budget,
+/// approvals)` tuple form keeps working.
+///
+/// ```
+/// use seq_phragmen::{seq_phragmen, Ballot};
+///
+/// let tuples = vec![
+///     ("A".to_string(), 10.0, vec!["X".to_string(), "Y".to_string()]),
+///     ("B".to_string(), 20.0, vec!["X".to_string(), "Z".to_string()]),
+///     ("C".to_string(), 30.0, vec!["Y".to_string(), "Z".to_string()]),
+/// ];
+/// let ballots = vec![
+///     Ballot::new("A", 10.0, ["X", "Y"]),
+///     Ballot::new("B", 20.0, ["X", "Z"]),
+///     Ballot::from_approval_counts("C", [("Y", 12.0), ("Z", 18.0)]),
+/// ];
+/// let from_tuples = seq_phragmen(tuples, 2);
+/// let from_ballots = seq_phragmen(ballots, 2);
+/// assert_eq!(from_tuples.outcome(), from_ballots.outcome());
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Ballot {
+    pub voter: String,
+    pub stake: f64,
+    pub approvals: Vec<String>,
+}
+
+impl Ballot {
+    pub fn new<S: Into<String>>(
+        voter: impl Into<String>,
+        stake: f64,
+        approvals: impl IntoIterator<Item = S>,
+    ) -> Self {
+        Ballot {
+            voter: voter.into(),
+            stake,
+            approvals: approvals.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// A ballot from raw per-candidate approval counts: the stake is their
+    /// sum and the ballot approves every listed candidate, in order.
+    pub fn from_approval_counts<S: Into<String>>(
+        voter: impl Into<String>,
+        counts: impl IntoIterator<Item = (S, f64)>,
+    ) -> Self {
+        let mut stake = 0.0;
+        let approvals = counts
+            .into_iter()
+            .map(|(canid, count)| {
+                stake += count;
+                canid.into()
+            })
+            .collect();
+        Ballot {
+            voter: voter.into(),
+            stake,
+            approvals,
+        }
+    }
+}
+
+impl From<(String, f64, Vec<String>)> for Ballot {
+    fn from((voter, stake, approvals): (String, f64, Vec<String>)) -> Self {
+        Ballot {
+            voter,
+            stake,
+            approvals,
+        }
+    }
+}
+
+impl From<Ballot> for (String, f64, Vec<String>) {
+    fn from(ballot: Ballot) -> Self {
+        (ballot.voter, ballot.stake, ballot.approvals)
+    }
+}
+
+fn into_votelist<B: Into<Ballot>>(ballots: Vec<B>) -> VoteList {
+    ballots
+        .into_iter()
+        .map(|ballot| ballot.into().into())
+        .collect()
+}
+
+/// Voters are ordered by id, since map iteration order is unspecified;
+/// error indices refer to that order.
+impl TryFrom<HashMap<String, (f64, Vec<String>)>> for VoteSet {
+    type Error = PhragmenError;
+
+    fn try_from(map: HashMap<String, (f64, Vec<String>)>) -> Result<Self, Self::Error> {
+        let mut votelist: VoteList = map
+            .into_iter()
+            .map(|(voterid, (budget, approvals))| (voterid, budget, approvals))
+            .collect();
+        votelist.sort_by(|x, y| x.0.cmp(&y.0));
+        for (index, (voterid, budget, _)) in votelist.iter().enumerate() {
+            check_budget(index, voterid, *budget)?;
+        }
+        Ok(VoteSet(votelist))
+    }
+}
+
+impl TryFrom<Vec<(&str, f64, Vec<&str>)>> for VoteSet {
+    type Error = PhragmenError;
+
+    fn try_from(votes: Vec<(&str, f64, Vec<&str>)>) -> Result<Self, Self::Error> {
+        votes
+            .into_iter()
+            .enumerate()
+            .map(|(index, (voterid, budget, approvals))| {
+                check_budget(index, voterid, budget)?;
+                Ok((
+                    voterid.to_string(),
+                    budget,
+                    approvals.into_iter().map(str::to_string).collect(),
+                ))
+            })
+            .collect::<Result<_, _>>()
+            .map(VoteSet)
+    }
+}
+
+/// Why `verify_solution` rejected a claimed solution.
+#[derive(Debug, Clone, PartialEq)]
+pub enum VerificationError {
+    UnknownVoter {
+        voterid: String,
+    },
+    UnknownWinner {
+        canid: String,
+    },
+    DuplicateWinner {
+        canid: String,
+    },
+    NotAWinner {
+        voterid: String,
+        canid: String,
+    },
+    NotApproved {
+        voterid: String,
+        canid: String,
+    },
+    NegativeWeight {
+        voterid: String,
+        canid: String,
+        weight: f64,
+    },
+    BudgetExceeded {
+        voterid: String,
+        budget: f64,
+        assigned: f64,
+    },
+}
+
+impl fmt::Display for VerificationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerificationError::UnknownVoter { voterid } => {
+                write!(f, "voter {voterid} is not on any ballot")
+            }
+            VerificationError::UnknownWinner { canid } => {
+                write!(f, "winner {canid} is not on any ballot")
+            }
+            VerificationError::DuplicateWinner { canid } => {
+                write!(f, "winner {canid} is listed more than once")
+            }
+            VerificationError::NotAWinner { voterid, canid } => {
+                write!(f, "voter {voterid} assigns weight to non-winner {canid}")
+            }
+            VerificationError::NotApproved { voterid, canid } => {
+                write!(
+                    f,
+                    "voter {voterid} assigns weight to {canid} without approving it"
+                )
+            }
+            VerificationError::NegativeWeight {
+                voterid,
+                canid,
+                weight,
+            } => write!(
+                f,
+                "voter {voterid} assigns negative weight {weight} to {canid}"
+            ),
+            VerificationError::BudgetExceeded {
+                voterid,
+                budget,
+                assigned,
+            } => write!(
+                f,
+                "voter {voterid} assigns {assigned} but has budget {budget}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for VerificationError {}
+
+/// Parses ballots written one per line as `voter:budget > cand1,cand2`.
+/// Whitespace around each part is ignored, as are blank lines and lines
+/// starting with `#`. An empty candidate list (`voter:budget >`) is an
+/// abstaining voter. Line numbers in errors start at 1.
+pub fn parse_text(input: &str) -> Result<VoteList, PhragmenError> {
+    let mut votelist = Vec::new();
+    for (number, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let error = |message: &str| PhragmenError::Parse {
+            line: number + 1,
+            message: message.to_string(),
+        };
+        let (voter, approvals) = line.split_once('>').ok_or_else(|| error("missing '>'"))?;
+        let (voterid, budget) = voter.split_once(':').ok_or_else(|| error("missing ':'"))?;
+        let voterid = voterid.trim();
+        if voterid.is_empty() {
+            return Err(error("empty voter id"));
+        }
+        let budget: f64 = budget
+            .trim()
+            .parse()
+            .map_err(|_| error("budget is not a number"))?;
+        check_budget(votelist.len(), voterid, budget)?;
+        let approvals = approvals.trim();
+        let approvals = if approvals.is_empty() {
+            Vec::new()
+        } else {
+            approvals
+                .split(',')
+                .map(|canid| match canid.trim() {
+                    "" => Err(error("empty candidate id")),
+                    canid => Ok(canid.to_string()),
+                })
+                .collect::<Result<_, _>>()?
+        };
+        votelist.push((voterid.to_string(), budget, approvals));
+    }
+    Ok(votelist)
+}
+
+/// Reads ballots back from the `source,target[,weight]` edge list written by
+/// `Assignment::write_edgelist_csv`, one row per approval with an optional
+/// header. With a weight column the weight is the voter's budget, and a row
+/// continues the previous ballot when it has the same source and weight.
+/// Without one, every approval is assumed to count equally: each voter gets
+/// budget 1.0 and the rows of one source form a single ballot. Voters
+/// without approvals have no rows and do not come back. Ids cannot contain
+/// commas. Line numbers in parse errors start at 1; the `index` of an
+/// `InvalidBudget` is the 0-based line.
+pub fn read_edgelist_csv(input: &str) -> Result<VoteList, PhragmenError> {
+    let mut votelist: VoteList = Vec::new();
+    for (number, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || (number == 0 && line.starts_with("source,")) {
+            continue;
+        }
+        let error = |message: &str| PhragmenError::Parse {
+            line: number + 1,
+            message: message.to_string(),
+        };
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let (voterid, canid, budget) = match fields[..] {
+            [voterid, canid] => (voterid, canid, 1.0),
+            [voterid, canid, weight] => {
+                let budget = weight
+                    .parse()
+                    .map_err(|_| error("weight is not a number"))?;
+                (voterid, canid, budget)
+            }
+            _ => return Err(error("expected source,target[,weight]")),
+        };
+        if voterid.is_empty() {
+            return Err(error("empty voter id"));
+        }
+        if canid.is_empty() {
+            return Err(error("empty candidate id"));
+        }
+        check_budget(number, voterid, budget)?;
+        match votelist.last_mut() {
+            Some(ballot)
+                if ballot.0 == voterid
+                    && ballot.1 == budget
+                    && !ballot.2.iter().any(|approved| approved == canid) =>
+            {
+                ballot.2.push(canid.to_string());
+            }
+            _ => votelist.push((voterid.to_string(), budget, vec![canid.to_string()])),
+        }
+    }
+    Ok(votelist)
+}
+
+impl std::str::FromStr for VoteSet {
+    type Err = PhragmenError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        parse_text(input).map(VoteSet)
+    }
+}
+
+/// Accumulates a `VoteList` one voter at a time, validating as it goes.
+/// The first problem found is kept and returned by `build`.
+///
+/// ```
+/// use seq_phragmen::ElectionBuilder;
+///
+/// let votelist = ElectionBuilder::new()
+///     .voter("A", 10.0).approves("X").approves("Y")
+///     .voter("B", 20.0).approves("X").approves("Z")
+///     .voter("C", 30.0).approves("Y").approves("Z")
+///     .voter("C", 50.0).approves("Z")
+///     .build()
+///     .unwrap();
+/// let literal = vec![
+///     ("A".to_string(), 10.0, vec!["X".to_string(), "Y".to_string()]),
+///     ("B".to_string(), 20.0, vec!["X".to_string(), "Z".to_string()]),
+///     ("C".to_string(), 30.0, vec!["Y".to_string(), "Z".to_string()]),
+///     ("C".to_string(), 50.0, vec!["Z".to_string()]),
+/// ];
+/// assert_eq!(votelist, literal);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ElectionBuilder {
+    votelist: VoteList,
+    error: Option<PhragmenError>,
+}
+
+impl ElectionBuilder {
+    pub fn new() -> Self {
+        ElectionBuilder::default()
+    }
+
+    /// Starts a new ballot; following `approves` calls add to it.
+    pub fn voter(mut self, voterid: impl Into<String>, budget: f64) -> Self {
+        let voterid = voterid.into();
+        if self.error.is_none() {
+            self.error = check_budget(self.votelist.len(), &voterid, budget).err();
+        }
+        self.votelist.push((voterid, budget, Vec::new()));
+        self
+    }
+
+    pub fn approves(mut self, canid: impl Into<String>) -> Self {
+        let canid = canid.into();
+        let index = self.votelist.len().saturating_sub(1);
+        match self.votelist.last_mut() {
+            Some((voterid, _, approvals)) => {
+                if approvals.contains(&canid) {
+                    if self.error.is_none() {
+                        self.error = Some(PhragmenError::DuplicateApproval {
+                            index,
+                            voterid: voterid.clone(),
+                            canid,
+                        });
+                    }
+                } else {
+                    approvals.push(canid);
+                }
+            }
+            None => {
+                if self.error.is_none() {
+                    self.error = Some(PhragmenError::ApprovalWithoutVoter { canid });
+                }
+            }
+        }
+        self
+    }
+
+    pub fn build(self) -> Result<VoteList, PhragmenError> {
+        match self.error {
+            Some(error) => Err(error),
+            None => Ok(self.votelist),
+        }
+    }
+}
+
+/// What `Election` does with a ballot whose voter id was already seen.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DuplicateVoters {
+    /// Keep it as a separate ballot, as `seq_phragmen` would.
+    #[default]
+    Separate,
+    /// Add its budget to the earlier ballot and append its new approvals.
+    Merge,
+    /// Replace the earlier ballot, keeping the earlier position.
+    Replace,
+}
+
+/// Ballots collected through `Extend`, then run as one election. Repeated
+/// approvals within a ballot are dropped; repeated voter ids are handled by
+/// the `DuplicateVoters` policy. As with `ElectionBuilder`, the first
+/// invalid budget is kept and returned by `run`.
+#[derive(Debug, Clone, Default)]
+pub struct Election {
+    votelist: VoteList,
+    duplicates: DuplicateVoters,
+    voterposition: HashMap<String, usize>,
+    received: usize,
+    options: PhragmenOptions,
+    error: Option<PhragmenError>,
+}
+
+impl Election {
+    pub fn new() -> Self {
+        Election::default()
+    }
+
+    pub fn with_duplicates(mut self, duplicates: DuplicateVoters) -> Self {
+        self.duplicates = duplicates;
+        self
+    }
+
+    pub fn with_options(mut self, options: PhragmenOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// The ballots collected so far.
+    pub fn votelist(&self) -> &[(String, f64, Vec<String>)] {
+        &self.votelist
+    }
+
+    pub fn run(&self, numtoelect: usize) -> Result<Assignment, PhragmenError> {
+        match &self.error {
+            Some(error) => Err(error.clone()),
+            None => Ok(seq_phragmen_with(
+                self.votelist.clone(),
+                numtoelect,
+                &self.options,
+            )),
+        }
+    }
+
+    fn push(&mut self, voterid: String, budget: f64, approvals: Vec<String>) {
+        if self.error.is_none() {
+            self.error = check_budget(self.received, &voterid, budget).err();
+        }
+        self.received += 1;
+        let mut deduped: Vec<String> = Vec::with_capacity(approvals.len());
+        for canid in approvals {
+            if !deduped.contains(&canid) {
+                deduped.push(canid);
+            }
+        }
+        let earlier = match self.duplicates {
+            DuplicateVoters::Separate => None,
+            _ => self.voterposition.get(&voterid).copied(),
+        };
+        match (earlier, self.duplicates) {
+            (Some(position), DuplicateVoters::Merge) => {
+                let ballot = &mut self.votelist[position];
+                ballot.1 += budget;
+                for canid in deduped {
+                    if !ballot.2.contains(&canid) {
+                        ballot.2.push(canid);
+                    }
+                }
+            }
+            (Some(position), _) => self.votelist[position] = (voterid, budget, deduped),
+            (None, _) => {
+                self.voterposition
+                    .entry(voterid.clone())
+                    .or_insert(self.votelist.len());
+                self.votelist.push((voterid, budget, deduped));
+            }
+        }
+    }
+}
+
+impl Extend<(String, f64, Vec<String>)> for Election {
+    fn extend<T: IntoIterator<Item = (String, f64, Vec<String>)>>(&mut self, ballots: T) {
+        for (voterid, budget, approvals) in ballots {
+            self.push(voterid, budget, approvals);
+        }
+    }
+}
+
+/// The most voters, and the most approvals in total, an election may have:
+/// edges store their indices as `u32`.
+pub const MAX_INDEX: usize = u32::MAX as usize;
+
+/// A voter's approval of a candidate. Edges only hold `u32` indices; the
+/// ids live once in `Assignment::voterlist` and `Assignment::candidates`.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Edge {
+    index: u32,
+    voterindex: u32,
+    canindex: u32,
+}
+
+impl Edge {
+    fn new(index: usize, voterindex: usize, canindex: usize) -> Self {
+        let compact = |value: usize| u32::try_from(value).expect("election exceeds u32 indices");
+        Edge {
+            index: compact(index),
+            voterindex: compact(voterindex),
+            canindex: compact(canindex),
+        }
+    }
+
+    /// Position of this edge in the assignment's edge vectors.
+    pub fn index(&self) -> usize {
+        self.index as usize
+    }
+
+    /// Index of the approving voter in `voterlist`.
+    pub fn voterindex(&self) -> usize {
+        self.voterindex as usize
+    }
+
+    /// Index of the approved candidate in `candidates`.
+    pub fn canindex(&self) -> usize {
+        self.canindex as usize
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Voter {
+    pub voterid: String,
+    pub budget: f64,
+    pub edges: Vec<Edge>,
+    pub index: usize,
+}
+
+impl Voter {
+    fn new(voterid: String, budget: f64, index: usize, numedges: usize) -> Self {
+        Voter {
+            voterid,
+            budget,
+            edges: Vec::with_capacity(numedges),
+            index,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Candidate {
+    pub canid: String,
+    pub index: usize,
+}
+
+impl Candidate {
+    fn new(canid: String, index: usize) -> Self {
+        Candidate { canid, index }
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Assignment {
+    pub(crate) voterlist: Vec<Voter>,
+    pub(crate) candidates: Vec<Candidate>,
+    edgelist: Vec<Edge>,
+    voterload: Vec<f64>,
+    edgeload: Vec<f64>,
+    edgeweight: Vec<f64>,
+    cansupport: Vec<f64>,
+    canelected: Vec<bool>,
+    canwithdrawn: Vec<bool>,
+    electedcandidates: HashSet<usize>,
+    electionorder: Vec<usize>,
+    canapproval: Vec<f64>,
+    canscore: Vec<f64>,
+    canscorenumerator: Vec<f64>,
+    canscoredenominator: Vec<f64>,
+    loaddeltas: Vec<Vec<(usize, f64)>>,
+    roundtrace: Vec<RoundTrace>,
+    droppedvoters: Vec<String>,
+    edgefraction: Vec<f64>,
+    eps: f64,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    canedges: Vec<Vec<u32>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    scoredfrom: Option<ScoreInputs>,
+    #[cfg(feature = "serde")]
+    candidatemeta: HashMap<String, serde_json::Value>,
+}
+
+impl Assignment {
+    pub fn new(
+        voterlist: Vec<Voter>,
+        candidates: Vec<Candidate>,
+        copyassignment: Option<&Assignment>,
+    ) -> Self {
+        if let Some(copy) = copyassignment {
+            Assignment {
+                voterlist: voterlist.clone(),
+                candidates: candidates.clone(),
+                edgelist: copy.edgelist.clone(),
+                voterload: copy.voterload.clone(),
+                edgeload: copy.edgeload.clone(),
+                edgeweight: copy.edgeweight.clone(),
+                cansupport: copy.cansupport.clone(),
+                canelected: copy.canelected.clone(),
+                canwithdrawn: copy.canwithdrawn.clone(),
+                electedcandidates: copy.electedcandidates.clone(),
+                electionorder: copy.electionorder.clone(),
+                canapproval: copy.canapproval.clone(),
+                canscore: copy.canscore.clone(),
+                canscorenumerator: copy.canscorenumerator.clone(),
+                canscoredenominator: copy.canscoredenominator.clone(),
+                loaddeltas: copy.loaddeltas.clone(),
+                roundtrace: copy.roundtrace.clone(),
+                droppedvoters: copy.droppedvoters.clone(),
+                edgefraction: copy.edgefraction.clone(),
+                eps: copy.eps,
+                canedges: copy.canedges.clone(),
+                scoredfrom: copy.scoredfrom.clone(),
+                #[cfg(feature = "serde")]
+                candidatemeta: copy.candidatemeta.clone(),
+            }
+        } else {
+            let mut canapproval = vec![0.0; candidates.len()];
+            for voter in &voterlist {
+                for edge in &voter.edges {
+                    canapproval[edge.canindex()] += voter.budget;
+                }
+            }
+            Assignment::with_approval(voterlist, candidates, canapproval)
+        }
+    }
+
+    /// Like `Assignment::new` without a copy, but takes each candidate's total
+    /// approval (indexed like `candidates`) instead of recomputing it. The
+    /// vector is trusted as given; keeping it consistent with the voters'
+    /// budgets is the caller's responsibility.
+    pub fn with_approval(
+        voterlist: Vec<Voter>,
+        candidates: Vec<Candidate>,
+        approval: Vec<f64>,
+    ) -> Self {
+        debug_assert_eq!(approval.len(), candidates.len());
+        let edgelist = voterlist
+            .iter()
+            .flat_map(|v| v.edges.clone())
+            .collect::<Vec<_>>();
+        let numvoters = voterlist.len();
+        let numcandidates = candidates.len();
+        let numedges = edgelist.len();
+        Assignment {
+            voterlist,
+            candidates,
+            edgelist,
+            voterload: vec![0.0; numvoters],
+            edgeload: vec![0.0; numedges],
+            edgeweight: vec![0.0; numedges],
+            cansupport: vec![0.0; numcandidates],
+            canelected: vec![false; numcandidates],
+            canwithdrawn: vec![false; numcandidates],
+            electedcandidates: HashSet::new(),
+            electionorder: Vec::new(),
+            canapproval: approval,
+            canscore: vec![0.0; numcandidates],
+            canscorenumerator: vec![0.0; numcandidates],
+            canscoredenominator: vec![1.0; numcandidates],
+            loaddeltas: Vec::new(),
+            roundtrace: Vec::new(),
+            droppedvoters: Vec::new(),
+            edgefraction: Vec::new(),
+            eps: EPS,
+            canedges: Vec::new(),
+            scoredfrom: None,
+            #[cfg(feature = "serde")]
+            candidatemeta: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn setload(&mut self, edge: &Edge, load: f64) {
+        let oldload = self.edgeload[edge.index()];
+        self.edgeload[edge.index()] = load;
+        self.voterload[edge.voterindex()] += load - oldload;
+    }
+
+    pub(crate) fn setweight(&mut self, edge: &Edge, weight: f64) {
+        let oldweight = self.edgeweight[edge.index()];
+        self.edgeweight[edge.index()] = weight;
+        self.cansupport[edge.canindex()] += weight - oldweight;
+    }
+
+    /// `loadstoweights_eps` with the assignment's threshold: the `eps` of
+    /// the options it was elected with, `EPS` otherwise.
+    pub fn loadstoweights(&mut self) {
+        self.loadstoweights_eps(self.eps);
+    }
+
+    /// Computes edge weights and supports from the current loads as
+    /// `seq_phragmen_with` does under `options` (`weight_distribution`,
+    /// `eps` and `max_support`), for assignments built with
+    /// `compute_weights` off; pass the options the election ran with.
+    /// Weights are set rather than added, so without `max_support` calling
+    /// this on an assignment that already has them changes nothing.
+    pub fn ensure_weights(&mut self, options: &PhragmenOptions) {
+        match options.weight_distribution {
+            WeightDistribution::AllEdges => self.loadstoweights_eps(options.eps),
+            WeightDistribution::WinnersOnly => self.loadstoweights_winners_only(options.eps),
+        }
+        if let Some(max_support) = options.max_support {
+            self.cap_support(max_support);
+        }
+    }
+
+    /// `loadstoweights`, treating voter loads at or below `eps` as zero.
+    pub fn loadstoweights_eps(&mut self, eps: f64) {
+        for voter_index in 0..self.voterlist.len() {
+            let voter = self.voterlist[voter_index].clone();
+            let voter_load = self.voterload[voter_index];
+            if voter_load > eps {
+                if self.edgefraction.is_empty() {
+                    for edge in &voter.edges {
+                        let weight = voter.budget * self.edgeload[edge.index()] / voter_load;
+                        self.setweight(edge, weight);
+                    }
+                } else {
+                    let graded_load = self.graded_load(&voter.edges, |_| true);
+                    for edge in &voter.edges {
+                        let edge_load = self.fraction(edge) * self.edgeload[edge.index()];
+                        self.setweight(edge, voter.budget * edge_load / graded_load);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Like `loadstoweights_eps`, but each voter's budget is split only over
+    /// their edges to elected candidates, in proportion to those edges'
+    /// loads; edges to unelected candidates get weight 0 even if they carry
+    /// load.
+    pub fn loadstoweights_winners_only(&mut self, eps: f64) {
+        for voter_index in 0..self.voterlist.len() {
+            let voter = self.voterlist[voter_index].clone();
+            let winner_load =
+                self.graded_load(&voter.edges, |edge| self.is_elected(edge.canindex()));
+            if winner_load > eps {
+                for edge in &voter.edges {
+                    let weight = if self.is_elected(edge.canindex()) {
+                        voter.budget * self.fraction(edge) * self.edgeload[edge.index()]
+                            / winner_load
+                    } else {
+                        0.0
+                    };
+                    self.setweight(edge, weight);
+                }
+            }
+        }
+    }
+
+    /// `weightstoloads_eps` with the assignment's threshold, as in
+    /// `loadstoweights`.
+    pub fn weightstoloads(&mut self) {
+        self.weightstoloads_eps(self.eps);
+    }
+
+    /// `weightstoloads`, treating candidate supports at or below `eps` as zero.
+    pub fn weightstoloads_eps(&mut self, eps: f64) {
+        for edge_index in 0..self.edgelist.len() {
+            let edge = self.edgelist[edge_index].clone();
+            let edge_weight = self.edgeweight[edge_index];
+            let can_support = self.cansupport[edge.canindex()];
+            if can_support > eps {
+                self.setload(&edge, edge_weight / can_support);
+            }
+        }
+    }
+
+    /// Caps every winner's support at `max_support` by moving weight off
+    /// it. Heuristic: each backer of an over-cap winner has their weight on
+    /// it scaled down to bring it to the cap, and the freed budget is spread
+    /// over that voter's other winners still below the cap, in proportion to
+    /// the weight already on them (equally if there is none). Budget a voter
+    /// cannot place this way is left unassigned. Runs one pass per winner at
+    /// most, since moved weight can push another winner over the cap. The
+    /// loads are then recomputed from the capped weights (`weightstoloads`),
+    /// so they describe the same assignment.
+    pub fn cap_support(&mut self, max_support: f64) {
+        for _ in 0..self.electionorder.len() {
+            let overcap: Vec<usize> = self
+                .electionorder
+                .iter()
+                .copied()
+                .filter(|&index| self.cansupport[index] > max_support)
+                .collect();
+            if overcap.is_empty() {
+                break;
+            }
+            for canindex in overcap {
+                let factor = max_support / self.cansupport[canindex];
+                for voterindex in 0..self.voterlist.len() {
+                    let voter = self.voterlist[voterindex].clone();
+                    let Some(capped) = voter.edges.iter().find(|e| e.canindex() == canindex) else {
+                        continue;
+                    };
+                    let weight = self.edgeweight[capped.index()];
+                    let freed = weight * (1.0 - factor);
+                    self.setweight(capped, weight * factor);
+                    let others: Vec<&Edge> = voter
+                        .edges
+                        .iter()
+                        .filter(|edge| {
+                            edge.canindex() != canindex
+                                && self.is_elected(edge.canindex())
+                                && self.cansupport[edge.canindex()] < max_support
+                        })
+                        .collect();
+                    if others.is_empty() {
+                        continue;
+                    }
+                    let otherweight: f64 = others
+                        .iter()
+                        .map(|edge| self.edgeweight[edge.index()])
+                        .sum();
+                    for edge in others.iter().copied() {
+                        let share = if otherweight > self.eps {
+                            self.edgeweight[edge.index()] / otherweight
+                        } else {
+                            1.0 / others.len() as f64
+                        };
+                        self.setweight(edge, self.edgeweight[edge.index()] + freed * share);
+                    }
+                }
+            }
+        }
+        self.weightstoloads();
+    }
+
+    /// Replaces the voters' budgets (indexed like `voterlist`) and recomputes
+    /// candidate approvals to match. Loads placed so far are kept.
+    pub fn set_budgets(&mut self, budgets: &[f64]) {
+        for (voter, &budget) in self.voterlist.iter_mut().zip(budgets) {
+            voter.budget = budget;
+        }
+        self.recompute_approval();
+    }
+
+    fn recompute_approval(&mut self) {
+        self.canapproval.fill(0.0);
+        for voter in &self.voterlist {
+            for edge in &voter.edges {
+                self.canapproval[edge.canindex()] += voter.budget * self.fraction(edge);
+            }
+        }
+    }
+
+    /// The share of its voter's budget `edge` approves with: the grade's
+    /// fraction in a `seq_phragmen_graded` run, 1.0 otherwise.
+    fn fraction(&self, edge: &Edge) -> f64 {
+        self.edgefraction.get(edge.index()).copied().unwrap_or(1.0)
+    }
+
+    /// Sum of `fraction * load` over the `edges` passing `keep`.
+    fn graded_load(&self, edges: &[Edge], keep: impl Fn(&Edge) -> bool) -> f64 {
+        edges
+            .iter()
+            .filter(|edge| keep(edge))
+            .map(|edge| self.fraction(edge) * self.edgeload[edge.index()])
+            .sum()
+    }
+
+    /// Returns the assignment to its state before any round: loads, weights,
+    /// supports and scores are zeroed, nobody is elected or withdrawn, and
+    /// recorded deltas and traces are dropped. Voters, candidates and the
+    /// allocations are kept, and approval is recomputed from the current
+    /// budgets, so a run after `set_budgets` and `reset` matches a fresh
+    /// build with the new budgets.
+    pub fn reset(&mut self) {
+        self.voterload.fill(0.0);
+        self.edgeload.fill(0.0);
+        self.edgeweight.fill(0.0);
+        self.cansupport.fill(0.0);
+        self.canelected.fill(false);
+        self.canwithdrawn.fill(false);
+        self.electedcandidates.clear();
+        self.electionorder.clear();
+        self.canscore.fill(0.0);
+        self.canscorenumerator.fill(0.0);
+        self.canscoredenominator.fill(1.0);
+        self.loaddeltas.clear();
+        self.roundtrace.clear();
+        self.recompute_approval();
+    }
+
+    /// Whether `candidates[index]` is elected. `elect` and `unelect` are the
+    /// only ways to change this, and all selection goes through it.
+    pub fn is_elected(&self, index: usize) -> bool {
+        self.canelected[index]
+    }
+
+    /// Whether each candidate is elected; index `i` corresponds to
+    /// `candidates[i]`. A plain slice, for membership checks in tight loops.
+    ///
+    /// ```
+    /// use std::collections::HashSet;
+    /// use seq_phragmen::seq_phragmen;
+    ///
+    /// let votelist = vec![
+    ///     ("A".to_string(), 10.0, vec!["X".to_string(), "Y".to_string()]),
+    ///     ("B".to_string(), 20.0, vec!["X".to_string(), "Z".to_string()]),
+    ///     ("C".to_string(), 30.0, vec!["Y".to_string(), "Z".to_string()]),
+    /// ];
+    /// let a = seq_phragmen(votelist, 2);
+    /// let winners: HashSet<usize> = a
+    ///     .elected_mask()
+    ///     .iter()
+    ///     .enumerate()
+    ///     .filter(|(_, &elected)| elected)
+    ///     .map(|(index, _)| index)
+    ///     .collect();
+    /// assert_eq!(&winners, a.elected_candidates());
+    /// ```
+    pub fn elected_mask(&self) -> &[bool] {
+        &self.canelected
+    }
+
+    /// Indices of the elected candidates.
+    pub fn elected_candidates(&self) -> &HashSet<usize> {
+        &self.electedcandidates
+    }
+
+    /// The number of candidates elected, which can be below the seats asked
+    /// for when candidates or constraints run out.
+    pub fn seats_filled(&self) -> usize {
+        self.electedcandidates.len()
+    }
+
+    /// Whether fewer than `requested` seats were filled.
+    pub fn was_truncated(&self, requested: usize) -> bool {
+        self.seats_filled() < requested
+    }
+
+    pub(crate) fn elect(&mut self, candidate: &Candidate) {
+        self.canelected[candidate.index] = true;
+        if self.electedcandidates.insert(candidate.index) {
+            self.electionorder.push(candidate.index);
+        }
+    }
+
+    pub(crate) fn unelect(&mut self, candidate: &Candidate) {
+        self.canelected[candidate.index] = false;
+        self.electedcandidates.remove(&candidate.index);
+        self.electionorder.retain(|&index| index != candidate.index);
+    }
+
+    /// Moves one voter's budget between their elected candidates so that
+    /// the supports of those they back come as close to equal as possible.
+    /// Returns the imbalance found before rebalancing: the spread between
+    /// the most supported backed winner and the least supported approved
+    /// winner, plus any budget left unassigned. Nothing is changed when that
+    /// is already below `tolerance`.
+    fn equalise(&mut self, voterindex: usize, tolerance: f64) -> f64 {
+        let voter = self.voterlist[voterindex].clone();
+        let mut electededges: Vec<Edge> = voter
+            .edges
+            .iter()
+            .filter(|edge| self.is_elected(edge.canindex()))
+            .cloned()
+            .collect();
+        if electededges.is_empty() {
+            return 0.0;
+        }
+        let stakeused: f64 = electededges
+            .iter()
+            .map(|edge| self.edgeweight[edge.index()])
+            .sum();
+        let minsupport = electededges
+            .iter()
+            .map(|edge| self.cansupport[edge.canindex()])
+            .fold(f64::INFINITY, f64::min);
+        let maxbacked = electededges
+            .iter()
+            .filter(|edge| self.edgeweight[edge.index()] > 0.0)
+            .map(|edge| self.cansupport[edge.canindex()])
+            .fold(f64::NEG_INFINITY, f64::max);
+        let difference = if maxbacked.is_finite() {
+            let difference = maxbacked - minsupport + voter.budget - stakeused;
+            if difference < tolerance {
+                return difference;
+            }
+            difference
+        } else {
+            voter.budget
+        };
+
+        for edge in &voter.edges {
+            self.setweight(edge, 0.0);
+        }
+        electededges.sort_by(|x, y| {
+            self.cansupport[x.canindex()].total_cmp(&self.cansupport[y.canindex()])
+        });
+        let mut cumulativesupport = 0.0;
+        let mut lastindex = electededges.len() - 1;
+        for (i, edge) in electededges.iter().enumerate() {
+            let support = self.cansupport[edge.canindex()];
+            if support * i as f64 - cumulativesupport > voter.budget {
+                lastindex = i - 1;
+                break;
+            }
+            cumulativesupport += support;
+        }
+        let lastsupport = self.cansupport[electededges[lastindex].canindex()];
+        let waystosplit = (lastindex + 1) as f64;
+        let excess = voter.budget + cumulativesupport - lastsupport * waystosplit;
+        for edge in &electededges[..=lastindex] {
+            let weight = excess / waystosplit + lastsupport - self.cansupport[edge.canindex()];
+            self.setweight(edge, weight);
+        }
+        difference
+    }
+
+    /// Balances the weights of an elected assignment: sweeps over all voters
+    /// moving budget from better to worse supported winners, until a sweep
+    /// finds every voter within `tolerance` or `max_iters` sweeps have run.
+    /// It also stops when the largest per-sweep change has stopped shrinking
+    /// over the last `OSCILLATION_WINDOW` sweeps and repeats with a short
+    /// period, since further sweeps would just cycle; more iterations or a
+    /// damped update may still help there. Loads are then recomputed from
+    /// the weights.
+    pub fn reach_equilibrium(&mut self, max_iters: usize, tolerance: f64) -> Equilibrium {
+        let mut changes = Vec::new();
+        let mut status = Equilibrium::MaxIterations {
+            iterations: max_iters,
+        };
+        while changes.len() < max_iters {
+            let mut maxdifference: f64 = 0.0;
+            for voterindex in 0..self.voterlist.len() {
+                let difference = self.equalise(voterindex, tolerance / 10.0);
+                maxdifference = maxdifference.max(difference);
+            }
+            changes.push(maxdifference);
+            if maxdifference < tolerance {
+                status = Equilibrium::Converged {
+                    iterations: changes.len(),
+                };
+                break;
+            }
+            if let Some(period) = oscillation_period(&changes) {
+                status = Equilibrium::Oscillating {
+                    iterations: changes.len(),
+                    period,
+                };
+                break;
+            }
+        }
+        self.weightstoloads();
+        status
+    }
+
+    /// Improves the winner set by swaps: each step tries replacing one winner
+    /// with one (non-withdrawn, approved) loser, balances every candidate set
+    /// the same way (each voter's budget split evenly over their winners,
+    /// then `reach_equilibrium`), and applies the swap that raises the
+    /// minimum support the most, until no swap raises it by more than a
+    /// relative `1e-9` or `max_swaps` swaps were made. The assignment is left
+    /// balanced even if no swap is made. Returns the number of swaps.
+    pub fn local_search(&mut self, max_swaps: usize) -> usize {
+        self.balance_winners();
+        let mut swaps = 0;
+        while swaps < max_swaps {
+            let current = self.election_score().0;
+            let mut best: Option<(f64, Assignment)> = None;
+            for &winner in &self.electionorder {
+                for loser in 0..self.candidates.len() {
+                    if self.is_elected(loser)
+                        || self.canwithdrawn[loser]
+                        || self.canapproval[loser] <= self.eps
+                    {
+                        continue;
+                    }
+                    let mut swapped = self.clone();
+                    swapped.unelect(&self.candidates[winner]);
+                    swapped.elect(&self.candidates[loser]);
+                    swapped.balance_winners();
+                    let minsupport = swapped.election_score().0;
+                    if best
+                        .as_ref()
+                        .is_none_or(|(bestmin, _)| minsupport > *bestmin)
+                    {
+                        best = Some((minsupport, swapped));
+                    }
+                }
+            }
+            match best {
+                Some((minsupport, swapped)) if minsupport > current + 1e-9 * current.abs() => {
+                    *self = swapped;
+                    swaps += 1;
+                }
+                _ => break,
+            }
+        }
+        swaps
+    }
+
+    /// Spreads each voter's budget evenly over the winners they approve and
+    /// balances the result, so winner sets can be compared by support. Edges
+    /// to losers are cleared.
+    fn balance_winners(&mut self) {
+        for voter in self.voterlist.clone() {
+            let winners = voter
+                .edges
+                .iter()
+                .filter(|edge| self.is_elected(edge.canindex()))
+                .count();
+            for edge in &voter.edges {
+                let weight = if self.is_elected(edge.canindex()) {
+                    voter.budget / winners as f64
+                } else {
+                    self.setload(edge, 0.0);
+                    0.0
+                };
+                self.setweight(edge, weight);
+            }
+        }
+        self.reach_equilibrium(100, 1e-9);
+    }
+
+    /// Checks that `loadstoweights` followed by `weightstoloads` leaves the
+    /// voter and edge loads unchanged to within `eps`, which holds for a
+    /// balanced assignment (e.g. after `reach_equilibrium`). Works on a copy;
+    /// `self` is not modified.
+    #[cfg(test)]
+    fn check_conversion_roundtrip(&self, eps: f64) -> bool {
+        let mut copy = self.clone();
+        copy.loadstoweights();
+        copy.weightstoloads();
+        let close = |x: &[f64], y: &[f64]| x.iter().zip(y).all(|(x, y)| (x - y).abs() <= eps);
+        close(&self.voterload, &copy.voterload) && close(&self.edgeload, &copy.edgeload)
+    }
+
+    /// Seeds this assignment's loads from `prev`. Voters are matched by id
+    /// (the n-th ballot under an id in `self` with the n-th in `prev`) and
+    /// their edges by candidate id; matched edges take `prev`'s load, all
+    /// others start at zero. Weights are recomputed from the new loads.
+    pub fn warm_start_from(&mut self, prev: &Assignment) {
+        let mut prevvoters: HashMap<(&str, usize), &Voter> = HashMap::new();
+        let mut seen: HashMap<&str, usize> = HashMap::new();
+        for voter in &prev.voterlist {
+            let occurrence = seen.entry(voter.voterid.as_str()).or_insert(0);
+            prevvoters.insert((voter.voterid.as_str(), *occurrence), voter);
+            *occurrence += 1;
+        }
+
+        let mut loads = vec![0.0; self.edgeload.len()];
+        let mut seen: HashMap<&str, usize> = HashMap::new();
+        for voter in &self.voterlist {
+            let occurrence = seen.entry(voter.voterid.as_str()).or_insert(0);
+            if let Some(prevvoter) = prevvoters.get(&(voter.voterid.as_str(), *occurrence)) {
+                for edge in &voter.edges {
+                    let canid = &self.candidates[edge.canindex()].canid;
+                    if let Some(prevedge) = prevvoter
+                        .edges
+                        .iter()
+                        .find(|prevedge| &prev.candidates[prevedge.canindex()].canid == canid)
+                    {
+                        loads[edge.index()] = prev.edgeload[prevedge.index()];
+                    }
+                }
+            }
+            *occurrence += 1;
+        }
+
+        for edge in self.edgelist.clone() {
+            self.setload(&edge, loads[edge.index()]);
+            self.setweight(&edge, 0.0);
+        }
+        self.loadstoweights();
+    }
+
+    /// Serialises the complete state, including loads, scores and election
+    /// order, so an interrupted run can be resumed with `load_state` and
+    /// `elect_next`.
+    #[cfg(feature = "serde")]
+    pub fn save_state(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn load_state(state: &str) -> Result<Assignment, serde_json::Error> {
+        serde_json::from_str(state)
+    }
+
+    /// Attaches arbitrary metadata (names, parties, ...) to candidates by id.
+    /// It is carried along for reporting only and never read by the
+    /// algorithm.
+    #[cfg(feature = "serde")]
+    pub fn with_candidate_meta(mut self, meta: HashMap<String, serde_json::Value>) -> Self {
+        self.candidatemeta = meta;
+        self
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn candidate_meta(&self, canid: &str) -> Option<&serde_json::Value> {
+        self.candidatemeta.get(canid)
+    }
+
+    /// A JSON summary: the winners in election order with their support and
+    /// metadata, plus all candidate metadata keyed by candidate id.
+    #[cfg(feature = "serde")]
+    pub fn json_report(&self) -> serde_json::Value {
+        self.json_report_with(None)
+    }
+
+    /// `json_report` with supports rounded to `precision` decimal places,
+    /// for presentation. Only the report is rounded; the assignment keeps
+    /// its full-precision values.
+    #[cfg(feature = "serde")]
+    pub fn json_report_rounded(&self, precision: usize) -> serde_json::Value {
+        self.json_report_with(Some(precision))
+    }
+
+    #[cfg(feature = "serde")]
+    fn json_report_with(&self, precision: Option<usize>) -> serde_json::Value {
+        let winners: Vec<serde_json::Value> = self
+            .electionorder
+            .iter()
+            .map(|&index| {
+                let canid = &self.candidates[index].canid;
+                let support = self.cansupport[index];
+                serde_json::json!({
+                    "id": canid,
+                    "support": precision.map_or(support, |precision| round_decimals(support, precision)),
+                    "meta": self.candidatemeta.get(canid),
+                })
+            })
+            .collect();
+        serde_json::json!({
+            "winners": winners,
+            "candidate_meta": self.candidatemeta,
+        })
+    }
+
+    /// Runs one round of sequential Phragmén: recomputes the score of every
+    /// unelected candidate, elects the lowest and raises its backers' loads.
+    /// Returns the elected candidate's index, or `None` if nobody is left to
+    /// elect. Weights are not updated; call `loadstoweights` when done.
+    pub fn elect_next(&mut self, options: &PhragmenOptions) -> Option<usize> {
+        self.elect_next_among(options, None)
+    }
+
+    /// Sets every unelected candidate's score to
+    /// `(1 + sum of budget * voterload over its backers) / approval`, the
+    /// load each backer would reach if it were elected now. Candidates with
+    /// no approval above `options.eps` score infinity. Approvals are divided
+    /// by `options.candidate_quota`; biases are not applied.
+    pub fn recompute_scores(&mut self, options: &PhragmenOptions) {
+        self.scoredfrom = None;
+        let numcandidates = self.candidates.len();
+        for canindex in 0..numcandidates {
+            if !self.is_elected(canindex) {
+                self.canscore[canindex] = if self.canapproval[canindex] > options.eps {
+                    1.0 / self.canapproval[canindex]
+                } else {
+                    f64::INFINITY
+                };
+            }
+        }
+        let mut compensation = vec![0.0; numcandidates];
+        for nom in &self.voterlist {
+            for edge in &nom.edges {
+                if !self.is_elected(edge.canindex())
+                    && self.canapproval[edge.canindex()] > options.eps
+                {
+                    let term = nom.budget * self.fraction(edge) * self.voterload[nom.index]
+                        / self.canapproval[edge.canindex()];
+                    if options.deterministic {
+                        kahan_add(
+                            &mut self.canscore[edge.canindex()],
+                            &mut compensation[edge.canindex()],
+                            term,
+                        );
+                    } else {
+                        self.canscore[edge.canindex()] += term;
+                    }
+                }
+            }
+        }
+        if !options.candidate_quota.is_empty() {
+            for candidate in &self.candidates {
+                if self.is_elected(candidate.index) {
+                    continue;
+                }
+                if let Some(&quota) = options.candidate_quota.get(&candidate.canid) {
+                    if quota.is_finite() && quota > 0.0 {
+                        self.canscore[candidate.index] *= quota;
+                    }
+                }
+            }
+        }
+    }
+
+    /// The score `recompute_scores` gives the unelected candidate at
+    /// `canindex`, summed over its backers through `canedges` in the same
+    /// order, so the two agree to the bit.
+    fn score_of(&self, canindex: usize, options: &PhragmenOptions) -> f64 {
+        let approval = self.canapproval[canindex];
+        if approval <= options.eps {
+            return f64::INFINITY;
+        }
+        let mut score = 1.0 / approval;
+        let mut compensation = 0.0;
+        for &edgeindex in &self.canedges[canindex] {
+            let edge = &self.edgelist[edgeindex as usize];
+            let nom = &self.voterlist[edge.voterindex()];
+            let term = nom.budget * self.fraction(edge) * self.voterload[nom.index] / approval;
+            if options.deterministic {
+                kahan_add(&mut score, &mut compensation, term);
+            } else {
+                score += term;
+            }
+        }
+        match options
+            .candidate_quota
+            .get(&self.candidates[canindex].canid)
+        {
+            Some(&quota) if quota.is_finite() && quota > 0.0 => score * quota,
+            _ => score,
+        }
+    }
+
+    /// Fills `canedges`, the edges of every candidate in voter order, unless
+    /// it is already built. Only `incremental_rescoring` needs it.
+    fn index_candidate_edges(&mut self) {
+        if self.canedges.len() == self.candidates.len() {
+            return;
+        }
+        let mut canedges = vec![Vec::new(); self.candidates.len()];
+        for edge in &self.edgelist {
+            canedges[edge.canindex()].push(edge.index);
+        }
+        self.canedges = canedges;
+    }
+
+    /// `recompute_scores` for `incremental_rescoring`: rescores only the
+    /// unelected candidates whose inputs changed since the scores were last
+    /// computed, i.e. those sharing a voter whose load or budget moved, those
+    /// whose approval changed and those unelected since. The first call, and
+    /// any call with options the scores depend on changed, does a full pass.
+    fn rescore_stale(&mut self, options: &PhragmenOptions) {
+        let inputs = ScoreInputs::of(self, options);
+        match self.scoredfrom.take() {
+            Some(previous) if previous.same_options(&inputs) => {
+                self.index_candidate_edges();
+                let mut stale: Vec<bool> = (0..self.candidates.len())
+                    .map(|canindex| {
+                        previous.elected[canindex]
+                            || previous.approval[canindex] != inputs.approval[canindex]
+                    })
+                    .collect();
+                for voter in &self.voterlist {
+                    if previous.voterload[voter.index] != inputs.voterload[voter.index]
+                        || previous.budgets[voter.index] != inputs.budgets[voter.index]
+                    {
+                        for edge in &voter.edges {
+                            stale[edge.canindex()] = true;
+                        }
+                    }
+                }
+                for (canindex, &stale) in stale.iter().enumerate() {
+                    if stale && !self.is_elected(canindex) {
+                        self.canscore[canindex] = self.score_of(canindex, options);
+                    }
+                }
+            }
+            _ => self.recompute_scores(options),
+        }
+        self.scoredfrom = Some(inputs);
+    }
+
+    /// The candidate of `tied` (indices, ascending) with the most ranking
+    /// budget behind it, see `TieBreak::VoterPreference`.
+    fn preferred_among(&self, tied: &[usize], rankings: &HashMap<String, Vec<String>>) -> usize {
+        let mut tally = vec![0.0; tied.len()];
+        for voter in &self.voterlist {
+            let Some(ranking) = rankings.get(&voter.voterid) else {
+                continue;
+            };
+            let preferred = ranking.iter().find_map(|canid| {
+                tied.iter().position(|&canindex| {
+                    self.candidates[canindex].canid == *canid
+                        && voter.edges.iter().any(|edge| edge.canindex() == canindex)
+                })
+            });
+            if let Some(position) = preferred {
+                tally[position] += voter.budget;
+            }
+        }
+        let mut best = 0;
+        for (position, &votes) in tally.iter().enumerate() {
+            if votes > tally[best] {
+                best = position;
+            }
+        }
+        tied[best]
+    }
+
+    /// Per candidate, the number of distinct voter ids approving it with a
+    /// budget above `eps`.
+    fn distinct_backers(&self, eps: f64) -> Vec<usize> {
+        let mut backers: Vec<HashSet<&str>> = vec![HashSet::new(); self.candidates.len()];
+        for voter in &self.voterlist {
+            if voter.budget > eps {
+                for edge in &voter.edges {
+                    backers[edge.canindex()].insert(voter.voterid.as_str());
+                }
+            }
+        }
+        backers.iter().map(HashSet::len).collect()
+    }
+
+    /// `elect_next`, only considering candidates whose entry in `allowed`
+    /// (indexed like `candidates`) is true.
+    fn elect_next_among(
+        &mut self,
+        options: &PhragmenOptions,
+        allowed: Option<&[bool]>,
+    ) -> Option<usize> {
+        if options.incremental_rescoring {
+            self.rescore_stale(options);
+        } else {
+            self.recompute_scores(options);
+        }
+        let projectedsupport: Vec<f64> = self.canscore.iter().map(|score| 1.0 / score).collect();
+        let selectionkey: Vec<f64> = self
+            .candidates
+            .iter()
+            .map(|candidate| {
+                let score = self.canscore[candidate.index];
+                match options.score_bias.get(&candidate.canid) {
+                    Some(bias) if !self.is_elected(candidate.index) => score + bias,
+                    _ => score,
+                }
+            })
+            .collect();
+        let backers = (options.min_backers > 1).then(|| self.distinct_backers(options.eps));
+        let mut bestcandidate = None;
+        let mut bestscore = f64::INFINITY;
+        let mut tied = Vec::new();
+        for (canindex, &projected) in projectedsupport.iter().enumerate() {
+            let clearsfloor = options.support_floor.is_none_or(|floor| projected >= floor);
+            let isallowed =
+                !self.canwithdrawn[canindex] && allowed.is_none_or(|allowed| allowed[canindex]);
+            let enoughbackers = backers
+                .as_ref()
+                .is_none_or(|backers| backers[canindex] >= options.min_backers);
+            if !self.is_elected(canindex) && clearsfloor && isallowed && enoughbackers {
+                match score_cmp(selectionkey[canindex], bestscore) {
+                    std::cmp::Ordering::Less => {
+                        bestscore = selectionkey[canindex];
+                        bestcandidate = Some(canindex);
+                        tied.clear();
+                        tied.push(canindex);
+                    }
+                    std::cmp::Ordering::Equal if bestcandidate.is_some() => tied.push(canindex),
+                    _ => {}
+                }
+            }
+        }
+        if tied.len() > 1 {
+            match options.tie_break {
+                TieBreak::LowestIndex => {}
+                TieBreak::Random { seed } => {
+                    let round = self.electionorder.len() as u64;
+                    let mut state = seed ^ round.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+                    let draw = splitmix64(&mut state) as u128 * tied.len() as u128;
+                    bestcandidate = Some(tied[(draw >> 64) as usize]);
+                }
+                TieBreak::VoterPreference => {
+                    bestcandidate = Some(self.preferred_among(&tied, &options.voter_rankings));
+                }
+            }
+        }
+        let bestcandidate = bestcandidate?;
+        let electedcandidate = self.candidates[bestcandidate].clone();
+        self.elect(&electedcandidate);
+        let mut deltas = Vec::new();
+        let mut updates = Vec::new();
+        let winneredges: Vec<Edge> = if self.canedges.len() == self.candidates.len() {
+            self.canedges[bestcandidate]
+                .iter()
+                .map(|&edgeindex| self.edgelist[edgeindex as usize].clone())
+                .collect()
+        } else {
+            self.edgelist
+                .iter()
+                .filter(|edge| edge.canindex() == bestcandidate)
+                .cloned()
+                .collect()
+        };
+        for edge in &winneredges {
+            let load = options.load_strategy.load(
+                options.seat_weight * self.canscore[bestcandidate],
+                self.voterload[edge.voterindex()],
+                self.canapproval[bestcandidate],
+            );
+            if options.record_load_deltas {
+                deltas.push((edge.index(), load - self.edgeload[edge.index()]));
+            }
+            if options.trace {
+                updates.push((edge.index(), load));
+            }
+            self.setload(edge, load);
+        }
+        if options.record_load_deltas {
+            self.loaddeltas.push(deltas);
+        }
+        if options.trace {
+            self.roundtrace.push(RoundTrace {
+                approvals: self.canapproval.clone(),
+                scores: selectionkey,
+                elected: electedcandidate.canid.clone(),
+                winning_score: self.canscore[bestcandidate],
+                load_updates: updates,
+            });
+        }
+        Some(bestcandidate)
+    }
+
+    /// All voters, indexed by `Voter::index`.
+    pub fn voters(&self) -> &[Voter] {
+        &self.voterlist
+    }
+
+    /// All candidates, indexed by `Candidate::index`.
+    pub fn candidates(&self) -> &[Candidate] {
+        &self.candidates
+    }
+
+    pub fn candidate(&self, index: usize) -> Option<&Candidate> {
+        self.candidates.get(index)
+    }
+
+    pub fn voter(&self, index: usize) -> Option<&Voter> {
+        self.voterlist.get(index)
+    }
+
+    pub fn candidate_by_id(&self, canid: &str) -> Option<&Candidate> {
+        self.candidates
+            .iter()
+            .find(|candidate| candidate.canid == canid)
+    }
+
+    /// The first voter with the given id.
+    pub fn voter_by_id(&self, voterid: &str) -> Option<&Voter> {
+        self.voterlist.iter().find(|voter| voter.voterid == voterid)
+    }
+
+    /// All edges, indexed by `Edge::index`.
+    pub fn edges(&self) -> &[Edge] {
+        &self.edgelist
+    }
+
+    /// Total approval per candidate, indexed like `candidates`.
+    pub fn approvals(&self) -> &[f64] {
+        &self.canapproval
+    }
+
+    /// Accumulated load per voter, indexed like `voterlist`.
+    pub fn voter_loads(&self) -> &[f64] {
+        &self.voterload
+    }
+
+    /// Load per edge, indexed by `Edge::index`.
+    pub fn edge_loads(&self) -> &[f64] {
+        &self.edgeload
+    }
+
+    /// Ids of the voters removed by `PhragmenOptions::drop_edgeless_voters`,
+    /// in ballot order.
+    pub fn dropped_voters(&self) -> &[String] {
+        &self.droppedvoters
+    }
+
+    /// One entry per round run with `trace` on.
+    pub fn trace(&self) -> &[RoundTrace] {
+        &self.roundtrace
+    }
+
+    /// Per round run with `record_load_deltas` on, the load each edge gained
+    /// as `(edge index, delta)`. Empty when recording was off.
+    pub fn load_deltas(&self) -> &[Vec<(usize, f64)>] {
+        &self.loaddeltas
+    }
+
+    /// Accumulated load of the first voter with the given id.
+    ///
+    /// ```
+    /// use seq_phragmen::seq_phragmen;
+    ///
+    /// let votelist = vec![
+    ///     ("A".to_string(), 10.0, vec!["X".to_string(), "Y".to_string()]),
+    ///     ("B".to_string(), 20.0, vec!["X".to_string()]),
+    /// ];
+    /// let a = seq_phragmen(votelist, 1);
+    /// let load = a.voter_load_of("A").unwrap();
+    /// assert!((load - 1.0 / 30.0).abs() < 1e-12);
+    /// assert_eq!(a.voter_load_of("nobody"), None);
+    /// ```
+    pub fn voter_load_of(&self, voterid: &str) -> Option<f64> {
+        self.voter_by_id(voterid)
+            .map(|voter| self.voterload[voter.index])
+    }
+
+    /// `(minimum support, total support, sum of squared supports)` over the
+    /// elected candidates, as used to rank competing solutions: a higher
+    /// minimum and total are better, a lower sum of squares is better.
+    /// The minimum is 0.0 when nobody is elected.
+    pub fn election_score(&self) -> (f64, f64, f64) {
+        let minimum = self.weakest_winner().map_or(0.0, |(_, support)| support);
+        let mut total = 0.0;
+        let mut squares = 0.0;
+        for &index in &self.electedcandidates {
+            total += self.cansupport[index];
+            squares += self.cansupport[index] * self.cansupport[index];
+        }
+        (minimum, total, squares)
+    }
+
+    /// Total budget not assigned to any winner: for each voter, their budget
+    /// minus the weight on their edges to elected candidates. Voters who
+    /// backed at least one winner are fully represented in a Phragmén
+    /// result, so this is essentially the budget of voters who backed only
+    /// losers (or nobody).
+    pub fn total_wasted_budget(&self) -> f64 {
+        self.voterlist
+            .iter()
+            .map(|voter| {
+                let used: f64 = voter
+                    .edges
+                    .iter()
+                    .filter(|edge| self.is_elected(edge.canindex()))
+                    .map(|edge| self.edgeweight[edge.index()])
+                    .sum();
+                (voter.budget - used).max(0.0)
+            })
+            .sum()
+    }
+
+    /// Per voter (in `voterlist` order), how well the outcome serves them:
+    /// the share of their budget that went to winners, weighted by those
+    /// winners' supports relative to the best supported winner,
+    ///
+    /// `sum over elected edges of (weight / budget) * (support / max_support)`
+    ///
+    /// clamped to `[0, 1]`. A voter whose whole budget backs the best
+    /// supported winner scores 1.0; budget left on losers or unassigned
+    /// counts for nothing, and budget on weaker winners for less. Voters
+    /// without budget score 0.0, as does everyone when no winner has
+    /// positive support.
+    pub fn voter_satisfaction(&self) -> Vec<(String, f64)> {
+        let maxsupport = self
+            .electionorder
+            .iter()
+            .map(|&index| self.cansupport[index])
+            .fold(0.0, f64::max);
+        self.voterlist
+            .iter()
+            .map(|voter| {
+                let satisfaction = if voter.budget <= 0.0 || maxsupport <= 0.0 {
+                    0.0
+                } else {
+                    let weighted: f64 = voter
+                        .edges
+                        .iter()
+                        .filter(|edge| self.is_elected(edge.canindex()))
+                        .map(|edge| {
+                            self.edgeweight[edge.index()] * self.cansupport[edge.canindex()]
+                                / maxsupport
+                        })
+                        .sum();
+                    (weighted / voter.budget).clamp(0.0, 1.0)
+                };
+                (voter.voterid.clone(), satisfaction)
+            })
+            .collect()
+    }
+
+    /// An efficiency-gap style measure of how lopsidedly budget was used:
+    ///
+    /// `(sum over winners of (support - min_support) - total_wasted_budget) / total_budget`
+    ///
+    /// where `min_support` is the weakest winner's support. The first term is
+    /// the "surplus" spent on winners beyond what the weakest seat needed,
+    /// the second the budget that elected nobody. Positive values mean
+    /// surplus dominates, negative that waste dominates, and 0.0 a balanced
+    /// (or empty) outcome.
+    pub fn efficiency_metric(&self) -> f64 {
+        let totalbudget: f64 = self.voterlist.iter().map(|voter| voter.budget).sum();
+        if totalbudget <= 0.0 {
+            return 0.0;
+        }
+        let minsupport = self.election_score().0;
+        let surplus: f64 = self
+            .electedcandidates
+            .iter()
+            .map(|&index| self.cansupport[index] - minsupport)
+            .sum();
+        (surplus - self.total_wasted_budget()) / totalbudget
+    }
+
+    /// Each winner, in election order, with the total load on its edges:
+    /// how much of the voters' load went into paying for that seat.
+    pub fn seat_costs(&self) -> Vec<(String, f64)> {
+        let mut costs = vec![0.0; self.candidates.len()];
+        for edge in &self.edgelist {
+            costs[edge.canindex()] += self.edgeload[edge.index()];
+        }
+        self.electionorder
+            .iter()
+            .map(|&index| (self.candidates[index].canid.clone(), costs[index]))
+            .collect()
+    }
+
+    /// The elected candidate with the smallest support, ties going to the
+    /// lowest candidate index.
+    pub fn weakest_winner(&self) -> Option<(&Candidate, f64)> {
+        self.candidates
+            .iter()
+            .filter(|candidate| self.is_elected(candidate.index))
+            .map(|candidate| (candidate, self.cansupport[candidate.index]))
+            .fold(None, |weakest, (candidate, support)| match weakest {
+                Some((_, best)) if best <= support => weakest,
+                _ => Some((candidate, support)),
+            })
+    }
+
+    /// Roughly how much approval `canid` would have to lose to be unseated:
+    /// the smallest `r` for which its score against the final loads, with
+    /// its own load removed and its backing scaled down uniformly from `A`
+    /// to `A - r`, rises above the winning score of the weakest other winner
+    /// (by support). With `S` the budget-weighted load of its backers and
+    /// `t` that score, this is `A - A / (t * A - S)`, floored at 0.0. Also
+    /// 0.0 if `canid` is already the weakest winner, or not a winner.
+    pub fn withdrawal_to_unseat(&self, canid: &str) -> f64 {
+        let Some(candidate) = self.candidate_by_id(canid) else {
+            return 0.0;
+        };
+        if !self.is_elected(candidate.index)
+            || self
+                .weakest_winner()
+                .is_some_and(|(weakest, _)| weakest.index == candidate.index)
+        {
+            return 0.0;
+        }
+        let Some(target) = self
+            .candidates
+            .iter()
+            .filter(|other| other.index != candidate.index && self.is_elected(other.index))
+            .map(|other| (self.cansupport[other.index], self.canscore[other.index]))
+            .min_by(|x, y| x.0.total_cmp(&y.0))
+            .map(|(_, score)| score)
+        else {
+            return 0.0;
+        };
+        let approval = self.canapproval[candidate.index];
+        let mut backedload = 0.0;
+        for voter in &self.voterlist {
+            for edge in &voter.edges {
+                if edge.canindex() == candidate.index {
+                    backedload += voter.budget
+                        * self.fraction(edge)
+                        * (self.voterload[voter.index] - self.edgeload[edge.index()]);
+                }
+            }
+        }
+        let denominator = target * approval - backedload;
+        if denominator <= 0.0 {
+            return 0.0;
+        }
+        (approval - approval / denominator).max(0.0)
+    }
+
+    /// Whether the unelected `loser` has a complaint against the outcome: its
+    /// backers could pay for it, on top of their final loads, with every one
+    /// of them ending below the highest load any winner was elected at.
+    /// That load is `(1 + sum of budget * voterload) / approval` over its
+    /// backers, the score it would get in one more round; a correctly run
+    /// election leaves it at or above the highest winning score, so `Some`
+    /// (beyond a relative `1e-9`) points to an inconsistent assignment.
+    /// `None` for winners, unknown ids and candidates without approval.
+    pub fn complaint(&self, loser: &str) -> Option<ComplaintInfo> {
+        let candidate = self.candidate_by_id(loser)?;
+        let approval = self.canapproval[candidate.index];
+        if self.is_elected(candidate.index) || approval <= self.eps {
+            return None;
+        }
+        let maxwinnerload = self
+            .electionorder
+            .iter()
+            .map(|&index| self.canscore[index])
+            .fold(f64::NEG_INFINITY, f64::max);
+        let mut numerator = 1.0;
+        let mut voters = Vec::new();
+        for voter in &self.voterlist {
+            if let Some(edge) = voter
+                .edges
+                .iter()
+                .find(|edge| edge.canindex() == candidate.index)
+            {
+                numerator += voter.budget * self.fraction(edge) * self.voterload[voter.index];
+                voters.push(voter.voterid.clone());
+            }
+        }
+        let load = numerator / approval;
+        (load < maxwinnerload * (1.0 - 1e-9)).then(|| ComplaintInfo {
+            canid: candidate.canid.clone(),
+            load,
+            max_winner_load: maxwinnerload,
+            voters,
+        })
+    }
+
+    /// Every candidate's score, ascending (ties by id): winners keep the
+    /// score they were elected with, the others are scored against the
+    /// final loads as if one more round were run.
+    pub fn final_scores(&self) -> Vec<(String, f64)> {
+        let mut scores: Vec<f64> = (0..self.candidates.len())
+            .map(|canindex| {
+                if self.is_elected(canindex) {
+                    self.canscore[canindex]
+                } else if self.canapproval[canindex] > self.eps {
+                    1.0 / self.canapproval[canindex]
+                } else {
+                    f64::INFINITY
+                }
+            })
+            .collect();
+        for voter in &self.voterlist {
+            for edge in &voter.edges {
+                let canindex = edge.canindex();
+                if !self.is_elected(canindex) && self.canapproval[canindex] > self.eps {
+                    scores[canindex] +=
+                        voter.budget * self.fraction(edge) * self.voterload[voter.index]
+                            / self.canapproval[canindex];
+                }
+            }
+        }
+        let mut scores: Vec<(String, f64)> = self
+            .candidates
+            .iter()
+            .map(|candidate| (candidate.canid.clone(), scores[candidate.index]))
+            .collect();
+        scores.sort_by(|x, y| x.1.total_cmp(&y.1).then_with(|| x.0.cmp(&y.0)));
+        scores
+    }
+
+    /// Every unelected candidate whose final score (see `final_scores`) is
+    /// within `eps` of the weakest winner's winning score, the highest score
+    /// any winner was elected with: the candidates the tie-break passed over
+    /// for the final seat. Ordered as in `final_scores`; empty when nobody
+    /// was elected.
+    pub fn last_seat_contenders(&self, eps: f64) -> Vec<(String, f64)> {
+        let Some(weakestscore) = self
+            .electionorder
+            .iter()
+            .map(|&index| self.canscore[index])
+            .max_by(f64::total_cmp)
+        else {
+            return Vec::new();
+        };
+        self.final_scores()
+            .into_iter()
+            .filter(|(canid, score)| {
+                (score - weakestscore).abs() <= eps
+                    && self
+                        .candidate_by_id(canid)
+                        .is_some_and(|candidate| !self.is_elected(candidate.index))
+            })
+            .collect()
+    }
+
+    /// Every `(winner, loser)` pair where the winner has strictly lower total
+    /// approval than the unelected loser: the answer to "why did X win over
+    /// Y who had more votes?" is then the loads Y's backers already carried.
+    /// Winners in election order, losers in candidate order.
+    pub fn approval_inversions(&self) -> Vec<(String, String)> {
+        let mut inversions = Vec::new();
+        for &winner in &self.electionorder {
+            for loser in &self.candidates {
+                if !self.is_elected(loser.index)
+                    && self.canapproval[winner] < self.canapproval[loser.index]
+                {
+                    inversions.push((self.candidates[winner].canid.clone(), loser.canid.clone()));
+                }
+            }
+        }
+        inversions
+    }
+
+    /// Candidate pairs whose sets of backers (by voter id) have a Jaccard
+    /// similarity above `threshold`, as `(canid, canid, similarity)` in
+    /// candidate order: likely duplicates of the same option. Candidates
+    /// without backers are never reported.
+    pub fn similar_candidates(&self, threshold: f64) -> Vec<(String, String, f64)> {
+        let mut backers: Vec<HashSet<&str>> = vec![HashSet::new(); self.candidates.len()];
+        for voter in &self.voterlist {
+            for edge in &voter.edges {
+                backers[edge.canindex()].insert(voter.voterid.as_str());
+            }
+        }
+        let mut pairs = Vec::new();
+        for (first, firstbackers) in backers.iter().enumerate() {
+            for (second, secondbackers) in backers.iter().enumerate().skip(first + 1) {
+                let union = firstbackers.union(secondbackers).count();
+                if union == 0 {
+                    continue;
+                }
+                let similarity =
+                    firstbackers.intersection(secondbackers).count() as f64 / union as f64;
+                if similarity > threshold {
+                    pairs.push((
+                        self.candidates[first].canid.clone(),
+                        self.candidates[second].canid.clone(),
+                        similarity,
+                    ));
+                }
+            }
+        }
+        pairs
+    }
+
+    /// Elected candidates with their final support, highest support first and
+    /// ties broken by id. Unlike election order, this reflects support after
+    /// the loads have been turned into weights.
+    pub fn winners_by_support(&self) -> Vec<(String, f64)> {
+        let mut winners: Vec<(String, f64)> = self
+            .electedcandidates
+            .iter()
+            .map(|&index| (self.candidates[index].canid.clone(), self.cansupport[index]))
+            .collect();
+        winners.sort_by(|x, y| y.1.total_cmp(&x.1).then_with(|| x.0.cmp(&y.0)));
+        winners
+    }
+
+    /// Each winner, in election order, with a governance vote proportional
+    /// to its support, scaled so the votes sum to `council_size`. All
+    /// winners get an equal share when no winner has positive support.
+    pub fn winner_voting_power(&self, council_size: f64) -> Vec<(String, f64)> {
+        let total: f64 = self
+            .electionorder
+            .iter()
+            .map(|&index| self.cansupport[index])
+            .sum();
+        let seats = self.electionorder.len() as f64;
+        self.electionorder
+            .iter()
+            .map(|&index| {
+                let power = if total > 0.0 {
+                    council_size * self.cansupport[index] / total
+                } else {
+                    council_size / seats
+                };
+                (self.candidates[index].canid.clone(), power)
+            })
+            .collect()
+    }
+
+    /// A horizontal bar chart of the winners' supports, one line per winner
+    /// in `winners_by_support` order: the id (padded to the longest), a bar
+    /// of `#` scaled so the highest support spans `width` characters, and
+    /// the support to two decimals.
+    pub fn ascii_chart(&self, width: usize) -> String {
+        let winners = self.winners_by_support();
+        let labelwidth = winners
+            .iter()
+            .map(|(canid, _)| canid.len())
+            .max()
+            .unwrap_or(0);
+        let maxsupport = winners.first().map_or(0.0, |(_, support)| *support);
+        let mut chart = String::new();
+        for (canid, support) in &winners {
+            let bar = if maxsupport > 0.0 {
+                (support / maxsupport * width as f64).round().max(0.0) as usize
+            } else {
+                0
+            };
+            chart.push_str(&format!(
+                "{canid:<labelwidth$} |{} {support:.2}\n",
+                "#".repeat(bar)
+            ));
+        }
+        chart
+    }
+
+    /// Rebuilds the ballots this assignment was constructed from, each
+    /// voter's approvals in candidate index order.
+    pub fn votelist(&self) -> VoteList {
+        self.voterlist
+            .iter()
+            .map(|voter| {
+                let approvals = voter
+                    .edges
+                    .iter()
+                    .map(|edge| self.candidates[edge.canindex()].canid.clone())
+                    .collect();
+                (voter.voterid.clone(), voter.budget, approvals)
+            })
+            .collect()
+    }
+
+    /// Writes the approval graph as a `source,target,weight` CSV edge list
+    /// with a header: one row per edge, voters as sources, candidates as
+    /// targets, and as weight the voter's budget, which is what the edge
+    /// contributes to the candidate's approval. `read_edgelist_csv` reads it
+    /// back.
+    pub fn write_edgelist_csv<W: std::io::Write>(&self, mut writer: W) -> std::io::Result<()> {
+        writeln!(writer, "source,target,weight")?;
+        for voter in &self.voterlist {
+            for edge in &voter.edges {
+                writeln!(
+                    writer,
+                    "{},{},{}",
+                    voter.voterid,
+                    self.candidates[edge.canindex()].canid,
+                    voter.budget
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Each winner, in election order, with the `(voterid, weight)` of every
+    /// positive-weight edge backing it, in voter order.
+    pub fn assignments_by_winner(&self) -> Vec<(String, Vec<(String, f64)>)> {
+        let mut backing: Vec<Vec<(String, f64)>> = vec![Vec::new(); self.candidates.len()];
+        for voter in &self.voterlist {
+            for edge in &voter.edges {
+                let weight = self.edgeweight[edge.index()];
+                if self.is_elected(edge.canindex()) && weight > 0.0 {
+                    backing[edge.canindex()].push((voter.voterid.clone(), weight));
+                }
+            }
+        }
+        self.electionorder
+            .iter()
+            .map(|&index| {
+                (
+                    self.candidates[index].canid.clone(),
+                    std::mem::take(&mut backing[index]),
+                )
+            })
+            .collect()
+    }
+
+    /// Integer edge weights for distributing `total` units: the units are
+    /// first split between the voters who placed weight on some edge, in
+    /// proportion to budget, then each voter's units over their edges with
+    /// positive weight in proportion to `edgeweight`, both times by largest
+    /// remainder, so every voter's units add up exactly to their integer
+    /// share. Leftover units go to the largest fractional parts, ties to the
+    /// earlier voter or edge. Voters whose edges carry no weight (e.g. those
+    /// who backed only losers) get no rows and no share, so the rows add up
+    /// to `total` whenever any edge carries weight. Rows are
+    /// `(voterid, canid, units)`.
+    pub fn round_weights_integer(&self, total: u128) -> Vec<(String, String, u128)> {
+        let weighted: Vec<(&Voter, Vec<&Edge>)> = self
+            .voterlist
+            .iter()
+            .filter_map(|voter| {
+                let edges: Vec<&Edge> = voter
+                    .edges
+                    .iter()
+                    .filter(|edge| self.edgeweight[edge.index()] > 0.0)
+                    .collect();
+                (!edges.is_empty()).then_some((voter, edges))
+            })
+            .collect();
+        let budgets: Vec<f64> = weighted.iter().map(|(voter, _)| voter.budget).collect();
+        let voterunits = largest_remainder(total, &budgets);
+        let mut rows = Vec::new();
+        for ((voter, edges), &units) in weighted.iter().zip(&voterunits) {
+            let weights: Vec<f64> = edges
+                .iter()
+                .map(|edge| self.edgeweight[edge.index()])
+                .collect();
+            for (edge, edgeunits) in edges.iter().zip(largest_remainder(units, &weights)) {
+                rows.push((
+                    voter.voterid.clone(),
+                    self.candidates[edge.canindex()].canid.clone(),
+                    edgeunits,
+                ));
+            }
+        }
+        rows
+    }
+
+    /// Reruns plain `seq_phragmen` for the same number of seats with the
+    /// voter at `voterindex` removed from the ballots.
+    pub fn without_voter(&self, voterindex: usize) -> Assignment {
+        let mut votelist = self.votelist();
+        if voterindex < votelist.len() {
+            votelist.remove(voterindex);
+        }
+        seq_phragmen(votelist, self.electedcandidates.len())
+    }
+
+    /// Ids of the voters whose individual removal (via `without_voter`)
+    /// drops `canid` from the winner set. Empty if `canid` did not win.
+    /// Costs one full rerun per voter.
+    pub fn pivotal_voters(&self, canid: &str) -> Vec<String> {
+        let elected = self
+            .electedcandidates
+            .iter()
+            .any(|&index| self.candidates[index].canid == canid);
+        if !elected {
+            return Vec::new();
+        }
+        self.voterlist
+            .iter()
+            .filter(|voter| {
+                let rerun = self.without_voter(voter.index);
+                !rerun
+                    .electedcandidates
+                    .iter()
+                    .any(|&index| rerun.candidates[index].canid == canid)
+            })
+            .map(|voter| voter.voterid.clone())
+            .collect()
+    }
+
+    /// Withdraws a candidate: it is unelected if it had won, the load and
+    /// weight on its edges are released back to its backers, and it is never
+    /// elected again. A replacement can then be elected with `elect_next`;
+    /// call `loadstoweights` afterwards to refresh the other weights.
+    /// Returns false if there is no candidate with this id.
+    pub fn withdraw(&mut self, canid: &str) -> bool {
+        let Some(candidate) = self.candidate_by_id(canid).cloned() else {
+            return false;
+        };
+        self.unelect(&candidate);
+        self.canwithdrawn[candidate.index] = true;
+        for edge in self.edgelist.clone() {
+            if edge.canindex() == candidate.index {
+                self.setload(&edge, 0.0);
+                self.setweight(&edge, 0.0);
+            }
+        }
+        true
+    }
+
+    /// The cheapest ways found to change the winner set, rerunning plain
+    /// `seq_phragmen` for the same number of seats: the smallest change to
+    /// one voter's budget (searched by bisection, down to zero and up by as
+    /// much as the total budget, to a relative `1e-6`), the losers whose
+    /// exclusion alone changes the result (excluding a winner always does),
+    /// and any candidates tied for the last seat within `1e-9`. Costs
+    /// dozens of reruns per voter.
+    pub fn flip_report(&self) -> FlipReport {
+        let votelist = self.votelist();
+        let numtoelect = self.electedcandidates.len();
+        let winnerset = |votelist: VoteList| {
+            let a = seq_phragmen(votelist, numtoelect);
+            let mut winners: Vec<String> = a
+                .electedcandidates
+                .iter()
+                .map(|&index| a.candidates[index].canid.clone())
+                .collect();
+            winners.sort();
+            winners
+        };
+        let baseline = winnerset(votelist.clone());
+        let totalbudget: f64 = votelist.iter().map(|v| v.1).sum();
+
+        let mut budget_change: Option<(String, f64)> = None;
+        for (voterindex, (voterid, budget, _)) in votelist.iter().enumerate() {
+            let flips = |newbudget: f64| {
+                let mut changed = votelist.clone();
+                changed[voterindex].1 = newbudget;
+                winnerset(changed) != baseline
+            };
+            for far in [0.0, budget + totalbudget] {
+                if !flips(far) {
+                    continue;
+                }
+                let (mut near, mut far) = (*budget, far);
+                while (far - near).abs() > 1e-6 * totalbudget.max(f64::MIN_POSITIVE) {
+                    let mid = (near + far) / 2.0;
+                    if flips(mid) {
+                        far = mid;
+                    } else {
+                        near = mid;
+                    }
+                }
+                let delta = far - budget;
+                if budget_change
+                    .as_ref()
+                    .is_none_or(|(_, best)| delta.abs() < best.abs())
+                {
+                    budget_change = Some((voterid.clone(), delta));
+                }
+            }
+        }
+
+        let exclusions = self
+            .candidates
+            .iter()
+            .filter(|candidate| !self.is_elected(candidate.index))
+            .filter(|candidate| {
+                let without = votelist
+                    .iter()
+                    .map(|(voterid, budget, approvals)| {
+                        let approvals = approvals
+                            .iter()
+                            .filter(|canid| **canid != candidate.canid)
+                            .cloned()
+                            .collect();
+                        (voterid.clone(), *budget, approvals)
+                    })
+                    .collect();
+                winnerset(without) != baseline
+            })
+            .map(|candidate| candidate.canid.clone())
+            .collect();
+        let last_seat_tie = self
+            .last_seat_contenders(1e-9)
+            .into_iter()
+            .map(|(canid, _)| canid)
+            .collect();
+        FlipReport {
+            budget_change,
+            exclusions,
+            last_seat_tie,
+        }
+    }
+
+    /// Summarises the assignment as plain ids and numbers.
+    pub fn outcome(&self) -> Outcome {
+        let winners = self
+            .electionorder
+            .iter()
+            .map(|&index| (self.candidates[index].canid.clone(), self.cansupport[index]))
+            .collect();
+        let assignments = self
+            .voterlist
+            .iter()
+            .map(|voter| {
+                let edges = voter
+                    .edges
+                    .iter()
+                    .filter(|edge| self.is_elected(edge.canindex()))
+                    .map(|edge| {
+                        (
+                            self.candidates[edge.canindex()].canid.clone(),
+                            self.edgeweight[edge.index()],
+                        )
+                    })
+                    .collect();
+                (voter.voterid.clone(), edges)
+            })
+            .collect();
+        let budgets = self.voterlist.iter().map(|voter| voter.budget).collect();
+        Outcome {
+            winners,
+            assignments,
+            budgets,
+        }
+    }
+}
+
+/// Splits `total` into integers in proportion to `shares` (non-negative,
+/// `f64` precision) by the largest remainder method. The parts always sum
+/// to `total`, unless every share is zero, in which case all parts are 0.
+fn largest_remainder(total: u128, shares: &[f64]) -> Vec<u128> {
+    let sum: f64 = shares.iter().sum();
+    if sum.is_nan() || sum <= 0.0 {
+        return vec![0; shares.len()];
+    }
+    let quotas: Vec<f64> = shares
+        .iter()
+        .map(|share| total as f64 * share / sum)
+        .collect();
+    let mut parts: Vec<u128> = quotas.iter().map(|quota| quota.floor() as u128).collect();
+    let mut order: Vec<usize> = (0..shares.len()).collect();
+    order.sort_by(|&x, &y| {
+        (quotas[y] - quotas[y].floor())
+            .total_cmp(&(quotas[x] - quotas[x].floor()))
+            .then(x.cmp(&y))
+    });
+    let mut assigned: u128 = parts.iter().sum();
+    for &index in order.iter().cycle() {
+        if assigned >= total {
+            break;
+        }
+        parts[index] += 1;
+        assigned += 1;
+    }
+    for &index in order.iter().rev().cycle() {
+        if assigned <= total {
+            break;
+        }
+        if parts[index] > 0 {
+            parts[index] -= 1;
+            assigned -= 1;
+        }
+    }
+    parts
+}
+
+/// Orders scores by `f64::total_cmp`, except that every `NaN` compares
+/// greater than any number (and equal to another `NaN`), so a poisoned score
+/// can never be the lowest.
+fn score_cmp(x: f64, y: f64) -> std::cmp::Ordering {
+    match (x.is_nan(), y.is_nan()) {
+        (false, false) => x.total_cmp(&y),
+        (true, true) => std::cmp::Ordering::Equal,
+        (true, false) => std::cmp::Ordering::Greater,
+        (false, true) => std::cmp::Ordering::Less,
+    }
+}
+
+/// What `Assignment::canscore` was last computed from, so that
+/// `incremental_rescoring` can tell which scores went stale.
+#[derive(Debug, Clone)]
+struct ScoreInputs {
+    voterload: Vec<f64>,
+    budgets: Vec<f64>,
+    approval: Vec<f64>,
+    elected: Vec<bool>,
+    eps: f64,
+    deterministic: bool,
+    candidate_quota: HashMap<String, f64>,
+}
+
+impl ScoreInputs {
+    fn of(a: &Assignment, options: &PhragmenOptions) -> Self {
+        ScoreInputs {
+            voterload: a.voterload.clone(),
+            budgets: a.voterlist.iter().map(|voter| voter.budget).collect(),
+            approval: a.canapproval.clone(),
+            elected: a.canelected.clone(),
+            eps: options.eps,
+            deterministic: options.deterministic,
+            candidate_quota: options.candidate_quota.clone(),
+        }
+    }
+
+    /// Whether the options that enter `score_of` are unchanged.
+    fn same_options(&self, other: &ScoreInputs) -> bool {
+        self.eps == other.eps
+            && self.deterministic == other.deterministic
+            && self.candidate_quota == other.candidate_quota
+    }
+}
+
+/// The arithmetic of one seat round, for audits. Vectors are indexed like
+/// `Assignment::candidates`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RoundTrace {
+    /// Total approval per candidate.
+    pub approvals: Vec<f64>,
+    /// Every candidate's score as compared this round, biases included;
+    /// earlier winners keep the score they were elected with.
+    pub scores: Vec<f64>,
+    /// Id of the candidate elected this round.
+    pub elected: String,
+    /// The winner's unbiased score, which its backers' loads are raised to.
+    pub winning_score: f64,
+    /// `(edge index, new load)` for each of the winner's edges.
+    pub load_updates: Vec<(usize, f64)>,
+}
+
+/// Sweeps `reach_equilibrium` looks back over when checking for oscillation.
+pub const OSCILLATION_WINDOW: usize = 8;
+
+/// How `Assignment::reach_equilibrium` stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Equilibrium {
+    /// A sweep found every voter within tolerance.
+    Converged { iterations: usize },
+    /// The per-sweep change kept cycling with this period.
+    Oscillating { iterations: usize, period: usize },
+    /// `max_iters` sweeps ran without either of the above.
+    MaxIterations { iterations: usize },
+}
+
+impl Equilibrium {
+    /// The number of sweeps run.
+    pub fn iterations(&self) -> usize {
+        match *self {
+            Equilibrium::Converged { iterations }
+            | Equilibrium::Oscillating { iterations, .. }
+            | Equilibrium::MaxIterations { iterations } => iterations,
+        }
+    }
+}
+
+/// The period with which the last `OSCILLATION_WINDOW` changes repeat, if
+/// they have stopped decreasing and do repeat with a period of at most half
+/// the window. A flat run of changes counts as period 1.
+fn oscillation_period(changes: &[f64]) -> Option<usize> {
+    if changes.len() < OSCILLATION_WINDOW {
+        return None;
+    }
+    let window = &changes[changes.len() - OSCILLATION_WINDOW..];
+    if window.windows(2).all(|pair| pair[1] < pair[0]) {
+        return None;
+    }
+    let scale = window.iter().fold(0.0, |max: f64, &change| max.max(change));
+    (1..=OSCILLATION_WINDOW / 2).find(|&period| {
+        window[period..]
+            .iter()
+            .zip(window)
+            .all(|(later, earlier)| (later - earlier).abs() <= 1e-9 * scale)
+    })
+}
+
+/// Decimal places supports are rounded to before fingerprinting.
+pub const FINGERPRINT_DECIMALS: usize = 6;
+
+impl Outcome {
+    /// SHA-256 over the winners sorted by id, each with its support rounded
+    /// to `FINGERPRINT_DECIMALS` decimal places (and `-0` folded into `0`).
+    /// Outcomes whose supports differ only below that precision hash the
+    /// same on every platform; assignments and budgets are not included.
+    pub fn fingerprint(&self) -> [u8; 32] {
+        use sha2::{Digest, Sha256};
+
+        let mut winners: Vec<&(String, f64)> = self.winners.iter().collect();
+        winners.sort_by(|x, y| x.0.cmp(&y.0));
+        let mut hasher = Sha256::new();
+        for (canid, support) in winners {
+            let mut support = format!("{support:.FINGERPRINT_DECIMALS$}");
+            if support.starts_with('-') && support[1..].chars().all(|c| c == '0' || c == '.') {
+                support.remove(0);
+            }
+            hasher.update((canid.len() as u64).to_le_bytes());
+            hasher.update(canid.as_bytes());
+            hasher.update((support.len() as u64).to_le_bytes());
+            hasher.update(support.as_bytes());
+        }
+        hasher.finalize().into()
+    }
+
+    /// A copy with every support and weight rounded to `precision` decimal
+    /// places, for printing. Budgets are left as they are; `self` keeps its
+    /// full-precision values.
+    pub fn rounded(&self, precision: usize) -> Outcome {
+        Outcome {
+            winners: self
+                .winners
+                .iter()
+                .map(|(canid, support)| (canid.clone(), round_decimals(*support, precision)))
+                .collect(),
+            assignments: self
+                .assignments
+                .iter()
+                .map(|(voterid, edges)| {
+                    let edges = edges
+                        .iter()
+                        .map(|(canid, weight)| (canid.clone(), round_decimals(*weight, precision)))
+                        .collect();
+                    (voterid.clone(), edges)
+                })
+                .collect(),
+            budgets: self.budgets.clone(),
+        }
+    }
+
+    /// The outcome as flat columns, see `OutcomeSoA`.
+    ///
+    /// ```
+    /// use seq_phragmen::run_election;
+    ///
+    /// let votelist = vec![
+    ///     ("A".to_string(), 10.0, vec!["X".to_string(), "Y".to_string()]),
+    ///     ("B".to_string(), 20.0, vec!["X".to_string(), "Z".to_string()]),
+    ///     ("C".to_string(), 30.0, vec!["Y".to_string(), "Z".to_string()]),
+    /// ];
+    /// let outcome = run_election(votelist, 2);
+    /// let soa = outcome.to_soa();
+    /// let winners: Vec<(String, f64)> = soa
+    ///     .winner_offsets
+    ///     .windows(2)
+    ///     .zip(&soa.winner_supports)
+    ///     .map(|(span, &support)| (soa.winner_ids[span[0]..span[1]].to_string(), support))
+    ///     .collect();
+    /// assert_eq!(winners, outcome.winners);
+    /// ```
+    pub fn to_soa(&self) -> OutcomeSoA {
+        let mut soa = OutcomeSoA {
+            winner_offsets: vec![0],
+            voter_offsets: vec![0],
+            ..Default::default()
+        };
+        let mut winnerindex: HashMap<&str, usize> = HashMap::with_capacity(self.winners.len());
+        for (index, (canid, support)) in self.winners.iter().enumerate() {
+            winnerindex.insert(canid, index);
+            soa.winner_ids.push_str(canid);
+            soa.winner_offsets.push(soa.winner_ids.len());
+            soa.winner_supports.push(*support);
+        }
+        for (index, ((voterid, edges), budget)) in
+            self.assignments.iter().zip(&self.budgets).enumerate()
+        {
+            soa.voter_ids.push_str(voterid);
+            soa.voter_offsets.push(soa.voter_ids.len());
+            soa.voter_budgets.push(*budget);
+            for (canid, weight) in edges {
+                if let Some(&winner) = winnerindex.get(canid.as_str()) {
+                    soa.assignment_voters.push(index);
+                    soa.assignment_winners.push(winner);
+                    soa.assignment_weights.push(*weight);
+                }
+            }
+        }
+        soa
+    }
+}
+
+#[cfg(feature = "parquet")]
+impl Outcome {
+    /// Writes the outcome as two Parquet files: an assignments table
+    /// (`voter`, `candidate`, `weight`) with one row per weighted edge, and
+    /// a winners table (`candidate`, `support`) in election order.
+    pub fn write_parquet<W: std::io::Write + Send>(
+        &self,
+        assignments: W,
+        winners: W,
+    ) -> Result<(), parquet::errors::ParquetError> {
+        use arrow_array::{Array, ArrayRef, Float64Array, RecordBatch, StringArray};
+        use arrow_schema::{Field, Schema};
+        use std::sync::Arc;
+
+        let write = |writer: W, columns: Vec<(&str, ArrayRef)>| {
+            let schema = Schema::new(
+                columns
+                    .iter()
+                    .map(|(name, column)| Field::new(*name, column.data_type().clone(), false))
+                    .collect::<Vec<_>>(),
+            );
+            let batch = RecordBatch::try_new(
+                Arc::new(schema),
+                columns.into_iter().map(|(_, column)| column).collect(),
+            )?;
+            let mut writer = parquet::arrow::ArrowWriter::try_new(writer, batch.schema(), None)?;
+            writer.write(&batch)?;
+            writer.close().map(|_| ())
+        };
+        let edges: Vec<(&str, &str, f64)> = self
+            .assignments
+            .iter()
+            .flat_map(|(voterid, edges)| {
+                edges
+                    .iter()
+                    .map(move |(canid, weight)| (voterid.as_str(), canid.as_str(), *weight))
+            })
+            .collect();
+        write(
+            assignments,
+            vec![
+                (
+                    "voter",
+                    Arc::new(StringArray::from_iter_values(edges.iter().map(|e| e.0))),
+                ),
+                (
+                    "candidate",
+                    Arc::new(StringArray::from_iter_values(edges.iter().map(|e| e.1))),
+                ),
+                (
+                    "weight",
+                    Arc::new(Float64Array::from_iter_values(edges.iter().map(|e| e.2))),
+                ),
+            ],
+        )?;
+        write(
+            winners,
+            vec![
+                (
+                    "candidate",
+                    Arc::new(StringArray::from_iter_values(
+                        self.winners.iter().map(|(canid, _)| canid.as_str()),
+                    )),
+                ),
+                (
+                    "support",
+                    Arc::new(Float64Array::from_iter_values(
+                        self.winners.iter().map(|(_, support)| *support),
+                    )),
+                ),
+            ],
+        )
+    }
+}
+
+/// What it would take to change an outcome, from `Assignment::flip_report`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FlipReport {
+    /// The voter whose budget change flips the winner set soonest, with the
+    /// (signed) change needed. `None` if no single budget change does.
+    pub budget_change: Option<(String, f64)>,
+    /// Losers whose exclusion alone changes the winner set.
+    pub exclusions: Vec<String>,
+    /// Losers tied with the weakest winner for the final seat.
+    pub last_seat_tie: Vec<String>,
+}
+
+/// A losing candidate's complaint, from `Assignment::complaint`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComplaintInfo {
+    pub canid: String,
+    /// The load its backers would reach if it were elected now.
+    pub load: f64,
+    /// The highest score any winner was elected with.
+    pub max_winner_load: f64,
+    /// Ids of the voters approving it, who would share the seat.
+    pub voters: Vec<String>,
+}
+
+/// Rounds to `precision` decimal places, leaving values that would
+/// overflow the scaling untouched.
+fn round_decimals(value: f64, precision: usize) -> f64 {
+    let scale = 10f64.powi(precision.min(i32::MAX as usize) as i32);
+    let rounded = (value * scale).round() / scale;
+    if rounded.is_finite() {
+        rounded
+    } else {
+        value
+    }
+}
+
+/// The result of an election, detached from the internal index structures.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Outcome {
+    /// Elected candidates with their final support, in election order.
+    pub winners: Vec<(String, f64)>,
+    /// Per voter (in input order), the weight placed on each elected
+    /// candidate they approve of.
+    pub assignments: Vec<(String, Vec<(String, f64)>)>,
+    /// Each voter's budget, parallel to `assignments`.
+    pub budgets: Vec<f64>,
+}
+
+/// An `Outcome` as parallel flat arrays, for handing across an FFI
+/// boundary without nested allocations. Ids are UTF-8 concatenated into
+/// one buffer per table: entry `i` spans bytes
+/// `offsets[i]..offsets[i + 1]`, so each offsets array has one more entry
+/// than its table has rows and starts at 0. Winners are in election order
+/// and voters in input order. Each assignment triple is a row across
+/// `assignment_voters`, `assignment_winners` and `assignment_weights`,
+/// holding indices into the voter and winner tables, grouped by voter in
+/// input order.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct OutcomeSoA {
+    pub winner_ids: String,
+    pub winner_offsets: Vec<usize>,
+    pub winner_supports: Vec<f64>,
+    pub voter_ids: String,
+    pub voter_offsets: Vec<usize>,
+    pub voter_budgets: Vec<f64>,
+    pub assignment_voters: Vec<usize>,
+    pub assignment_winners: Vec<usize>,
+    pub assignment_weights: Vec<f64>,
+}
+
+/// A labelled sequence of outcomes, e.g. one per monthly election.
+#[derive(Debug, Clone, Default)]
+pub struct ElectionHistory {
+    runs: Vec<(String, Outcome)>,
+}
+
+impl ElectionHistory {
+    pub fn new() -> Self {
+        ElectionHistory::default()
+    }
+
+    pub fn push(&mut self, outcome: Outcome, label: String) {
+        self.runs.push((label, outcome));
+    }
+
+    pub fn len(&self) -> usize {
+        self.runs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.runs.is_empty()
+    }
+
+    /// The candidate's support in each run, in push order, or `None` for
+    /// runs it did not win.
+    pub fn support_series(&self, canid: &str) -> Vec<(String, Option<f64>)> {
+        self.runs
+            .iter()
+            .map(|(label, outcome)| {
+                let support = outcome
+                    .winners
+                    .iter()
+                    .find(|(winner, _)| winner == canid)
+                    .map(|&(_, support)| support);
+                (label.clone(), support)
+            })
+            .collect()
+    }
+}
+
+/// Assigns indices: candidates in order of first appearance on the ballots,
+/// edges consecutively. Each voter's edges are sorted by candidate index, so
+/// per-voter loops walk the candidate arrays in order.
+///
+/// # Panics
+///
+/// If there are more than `MAX_INDEX` voters or approvals in total;
+/// `try_setuplists` returns an error instead.
+pub fn setuplists<B: Into<Ballot>>(votelist: Vec<B>) -> (Vec<Voter>, Vec<Candidate>) {
+    setuplists_with_capacity(into_votelist(votelist), 0, 0)
+}
+
+/// `setuplists` with every candidate id (and, if `normalize_voters`, every
+/// voter id) passed through `normalize` first, so aliases such as `"x "`
+/// and `"X"` under a trimming, lowercasing normalizer become one candidate.
+/// Normalization happens before anything else: approvals that collapse to
+/// the same id within a ballot are deduplicated, keeping the first, and
+/// approval is then counted once per voter. Candidates keep the normalized
+/// id.
+pub fn setuplists_normalized<B: Into<Ballot>>(
+    votelist: Vec<B>,
+    normalize: impl Fn(&str) -> String,
+    normalize_voters: bool,
+) -> (Vec<Voter>, Vec<Candidate>) {
+    let votelist = into_votelist(votelist)
+        .into_iter()
+        .map(|(voterid, budget, approvals)| {
+            let voterid = if normalize_voters {
+                normalize(&voterid)
+            } else {
+                voterid
+            };
+            let mut normalized: Vec<String> = Vec::with_capacity(approvals.len());
+            for canid in approvals {
+                let canid = normalize(&canid);
+                if !normalized.contains(&canid) {
+                    normalized.push(canid);
+                }
+            }
+            (voterid, budget, normalized)
+        })
+        .collect();
+    setuplists(votelist)
+}
+
+/// `setuplists`, rejecting invalid budgets, repeated approvals within a
+/// ballot, and elections beyond `MAX_INDEX`. Errors carry the offending
+/// ballot's position in `votelist`.
+pub fn try_setuplists<B: Into<Ballot>>(
+    votelist: Vec<B>,
+) -> Result<(Vec<Voter>, Vec<Candidate>), PhragmenError> {
+    let votelist = into_votelist(votelist);
+    check_index_limits(votelist.len(), votelist.iter().map(|v| v.2.len()).sum())?;
+    for (index, (voterid, budget, approvals)) in votelist.iter().enumerate() {
+        check_budget(index, voterid, *budget)?;
+        let mut seen = HashSet::with_capacity(approvals.len());
+        for canid in approvals {
+            if !seen.insert(canid) {
+                return Err(PhragmenError::DuplicateApproval {
+                    index,
+                    voterid: voterid.clone(),
+                    canid: canid.clone(),
+                });
+            }
+        }
+    }
+    Ok(setuplists(votelist))
+}
+
+/// Same as `setuplists`, but reserves the voter list, candidate list and
+/// candidate map up front. The hints are only reservations: inaccurate hints
+/// cost an extra reallocation, never a different result. Each voter's edges
+/// are already reserved from its ballot, so edges need no hint.
+pub fn setuplists_with_capacity<B: Into<Ballot>>(
+    votelist: Vec<B>,
+    candidate_hint: usize,
+    voter_hint: usize,
+) -> (Vec<Voter>, Vec<Candidate>) {
+    let votelist = into_votelist(votelist);
+    let mut lists = VoterGraph {
+        voterlist: Vec::with_capacity(voter_hint),
+        candidatedict: HashMap::with_capacity(candidate_hint),
+        candidatearray: Vec::with_capacity(candidate_hint),
+        numedges: 0,
+    };
+    for (voterid, budget, approvals) in votelist {
+        lists.push(voterid, budget, approvals);
+    }
+    (lists.voterlist, lists.candidatearray)
+}
+
+/// The voter and candidate lists under construction, as built by
+/// `setuplists` one ballot at a time.
+#[derive(Debug, Clone, Default)]
+struct VoterGraph {
+    voterlist: Vec<Voter>,
+    candidatedict: HashMap<String, usize>,
+    candidatearray: Vec<Candidate>,
+    numedges: usize,
+}
+
+impl VoterGraph {
+    fn push(&mut self, voterid: String, budget: f64, approvals: Vec<String>) {
+        let mut voter = Voter::new(voterid, budget, self.voterlist.len(), approvals.len());
+        let mut canindices: Vec<usize> = approvals
+            .into_iter()
+            .map(|canid| {
+                if let Some(&canindex) = self.candidatedict.get(&canid) {
+                    canindex
+                } else {
+                    let canindex = self.candidatearray.len();
+                    self.candidatedict.insert(canid.clone(), canindex);
+                    self.candidatearray.push(Candidate::new(canid, canindex));
+                    canindex
+                }
+            })
+            .collect();
+        canindices.sort();
+        for canindex in canindices {
+            voter
+                .edges
+                .push(Edge::new(self.numedges, voter.index, canindex));
+            self.numedges += 1;
+        }
+        self.voterlist.push(voter);
+    }
+}
+
+/// A rough estimate, in bytes, of the heap an `Assignment` for an election
+/// of this size occupies, plus the candidate id table `setuplists` builds:
+/// `size_of` each element times the element count for every vector (edges
+/// are stored twice, in the voters and in the flat edge list), and the hash
+/// tables at one entry per candidate. Only the `String` headers of ids are
+/// counted, not their text, and allocator overhead and spare capacity are
+/// ignored, so treat the result as a lower bound for capacity planning.
+pub fn estimate_memory(num_voters: usize, num_candidates: usize, num_edges: usize) -> usize {
+    use std::mem::size_of;
+
+    let voters = num_voters * (size_of::<Voter>() + size_of::<f64>());
+    let edges = num_edges * (2 * size_of::<Edge>() + 2 * size_of::<f64>());
+    let candidates = num_candidates
+        * (size_of::<Candidate>()
+            + 5 * size_of::<f64>()
+            + 2 * size_of::<bool>()
+            + 2 * size_of::<usize>());
+    let idtable = num_candidates * (size_of::<String>() + size_of::<usize>());
+    voters + edges + candidates + idtable
+}
+
+/// Ballots arriving one at a time, e.g. during an open voting window. The
+/// voter and candidate lists grow with each vote; the election itself is
+/// rerun in full when winners are asked for, and only if votes arrived (or
+/// the seat count changed) since the last query.
+#[derive(Debug, Clone, Default)]
+pub struct LiveElection {
+    lists: VoterGraph,
+    cached: Option<(usize, Vec<String>)>,
+}
+
+impl LiveElection {
+    pub fn new() -> Self {
+        LiveElection::default()
+    }
+
+    pub fn add_vote(&mut self, ballot: impl Into<Ballot>) {
+        let Ballot {
+            voter,
+            stake,
+            approvals,
+        } = ballot.into();
+        self.lists.push(voter, stake, approvals);
+        self.cached = None;
+    }
+
+    /// The winners, in election order, as `seq_phragmen` would elect them
+    /// from the votes added so far.
+    pub fn current_winners(&mut self, numtoelect: usize) -> &[String] {
+        if self
+            .cached
+            .as_ref()
+            .is_none_or(|(seats, _)| *seats != numtoelect)
+        {
+            let mut a = Assignment::new(
+                self.lists.voterlist.clone(),
+                self.lists.candidatearray.clone(),
+                None,
+            );
+            let options = PhragmenOptions::default();
+            for _ in 0..numtoelect {
+                if a.elect_next(&options).is_none() {
+                    break;
+                }
+            }
+            let winners = a
+                .electionorder
+                .iter()
+                .map(|&index| a.candidates[index].canid.clone())
+                .collect();
+            self.cached = Some((numtoelect, winners));
+        }
+        self.cached.as_ref().map_or(&[], |(_, winners)| winners)
+    }
+}
+
+/// Optional knobs for `seq_phragmen_with`. The default reproduces plain
+/// `seq_phragmen`.
+#[derive(Debug, Clone)]
+pub struct PhragmenOptions {
+    /// Added to a candidate's score every round, after the score has been
+    /// recomputed and before the best candidate is picked. The lowest score
+    /// wins, so a negative bias favours a candidate (e.g. an incumbency
+    /// bonus) and a positive bias penalises it. Candidates are keyed by id;
+    /// ids not present in the election are ignored. The bias only decides
+    /// who is elected: loads, `support_floor` and the scores kept on the
+    /// assignment all use the unbiased score.
+    pub score_bias: HashMap<String, f64>,
+    /// Sorts voters by id and each voter's approvals by candidate id before
+    /// indices are assigned, and accumulates scores with compensated
+    /// summation. This fully decouples the output from the input order, so
+    /// permutations of the same ballots give bit-identical results, at the
+    /// cost of a sort and slightly slower score accumulation.
+    pub deterministic: bool,
+    /// Multiplier on the winning score when it is turned into load, i.e. a
+    /// winner's backers are raised to `seat_weight * score` instead of
+    /// `score`. Values above 1.0 make every seat more expensive for the
+    /// voters who fill it, so backers of early winners carry more load into
+    /// later rounds and lose influence faster than their budget alone would
+    /// warrant; values below 1.0 do the opposite. Either way the outcome is
+    /// no longer proportional in the usual Phragmén sense. Defaults to 1.0.
+    pub seat_weight: f64,
+    /// Threshold below which approvals, loads and supports count as zero.
+    /// Defaults to `EPS`. The assignment keeps it, so the analyses run on it
+    /// afterwards (`final_scores`, `complaint`, `local_search`,
+    /// `cap_support`, and `loadstoweights`/`weightstoloads` without an
+    /// explicit threshold) use the same one.
+    pub eps: f64,
+    /// Minimum support a candidate must be projected to receive to be
+    /// elected. A candidate elected with score `s` starts with support
+    /// exactly `1 / s` (the unbiased score), so candidates below the floor
+    /// are skipped in favour of the next eligible one. When nobody clears
+    /// the floor the election stops early, returning fewer winners than
+    /// requested. Final supports can still drift below the floor as later
+    /// rounds redistribute load.
+    pub support_floor: Option<f64>,
+    /// Candidates whose total approval is below this are removed, with every
+    /// edge to them, before indices are assigned, so they can neither win
+    /// nor take part in score computation.
+    pub min_approval: Option<f64>,
+    /// Minimum number of distinct voters (by id, with a positive budget) a
+    /// candidate needs behind it to be elected; candidates short of it are
+    /// skipped each round however much approval they have, so a candidate
+    /// carried by one large voter can lose to a less approved one with more
+    /// backers. 0 and 1 impose nothing beyond having a backer.
+    pub min_backers: usize,
+    /// Budget pools as `(poolid, budget, member voter ids)`: each pool's
+    /// members are merged into one voter, see `merge_pools`. Applied first,
+    /// before any candidate filtering.
+    pub pools: Vec<(String, f64, Vec<String>)>,
+    /// Restricts the election to these candidate ids: every edge to any
+    /// other candidate is dropped before indices are assigned. Applied
+    /// before `exclude`.
+    pub include_only: Option<HashSet<String>>,
+    /// Candidate ids whose edges are dropped before indices are assigned.
+    pub exclude: HashSet<String>,
+    /// Whether to turn the final loads into edge weights and supports.
+    /// Callers that only need the winner set can switch this off to skip
+    /// that pass and call `Assignment::ensure_weights` with the same options
+    /// later if needed.
+    /// Defaults to true.
+    pub compute_weights: bool,
+    /// How the final loads are turned into weights.
+    pub weight_distribution: WeightDistribution,
+    /// Group of each candidate, by candidate id, for `min_per_group`.
+    pub candidate_group: HashMap<String, String>,
+    /// Seats reserved per group. Once the seats left only just cover the
+    /// groups still short of their minimum, selection is restricted to
+    /// candidates of those groups, even when others score better.
+    /// `try_seq_phragmen_with` rejects minimums that cannot all be met;
+    /// `seq_phragmen_with` applies them best-effort and stops early if the
+    /// restricted candidates run out.
+    pub min_per_group: HashMap<String, usize>,
+    /// Classic unweighted Phragmén: every voter's budget, a pool's included,
+    /// is replaced by 1.0 right after `pools` are merged and before any
+    /// other filtering, so the input budgets are ignored entirely, by
+    /// `min_approval` as well.
+    pub approval_mode: bool,
+    /// Per-candidate divisor on approval, modelling seats of different
+    /// sizes: a candidate's approval counts as `approval / quota`, which
+    /// multiplies its score by `quota`, so a larger quota makes it harder to
+    /// elect. Quotas that are zero, negative or not finite are ignored
+    /// (treated as 1.0) rather than producing infinite approvals.
+    pub candidate_quota: HashMap<String, f64>,
+    /// How the winner's score is turned into load on its backers' edges.
+    pub load_strategy: LoadStrategy,
+    /// How a round picks among candidates with exactly the same score.
+    pub tie_break: TieBreak,
+    /// Optional fallback ranking per voter id, most preferred first, used
+    /// only by `TieBreak::VoterPreference`.
+    pub voter_rankings: HashMap<String, Vec<String>>,
+    /// Exact incremental rescoring for large candidate pools: after the
+    /// first round, only candidates whose score can have changed are
+    /// rescored (those sharing a voter with the previous winner, or touched
+    /// by changes between `elect_next` calls such as `withdraw` or
+    /// `set_budgets`), each from its own backers through a candidate-to-edge
+    /// index built once. Everyone else keeps a score that is still current,
+    /// so the scores and winners are exactly those of a full rescore. Picking
+    /// the best candidate still scans them all.
+    pub incremental_rescoring: bool,
+    /// Caps each winner's final support, see `Assignment::cap_support`.
+    /// Applied once the loads have been turned into weights, so it has no
+    /// effect with `compute_weights` off.
+    pub max_support: Option<f64>,
+    /// Records a full `RoundTrace` of every round, for `Assignment::trace`.
+    pub trace: bool,
+    /// Records the load change of every edge touched in each round, for
+    /// `Assignment::load_deltas`.
+    pub record_load_deltas: bool,
+    /// Removes voters left without approvals (after `include_only`,
+    /// `exclude` and `min_approval`) before the lists are built, listing
+    /// them in `Assignment::dropped_voters`. Kept, such a voter stays in
+    /// `voterlist` with zero load and no edges: an abstention that counts
+    /// towards the voters but backs no one. Either way no candidate's
+    /// approval changes.
+    pub drop_edgeless_voters: bool,
+}
+
+/// How a voter's budget is spread over their edges once the winners are
+/// fixed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WeightDistribution {
+    /// Over all edges, in proportion to edge load (`loadstoweights`).
+    #[default]
+    AllEdges,
+    /// Over edges to winners only, in proportion to edge load
+    /// (`loadstoweights_winners_only`). In a plain run only winners ever
+    /// receive load, so the two agree; they differ once losing edges carry
+    /// load (e.g. a custom `LoadStrategy` or an unelected candidate), where
+    /// `AllEdges` leaves that share of the budget on losers and
+    /// `WinnersOnly` moves it onto the voter's winners, raising their
+    /// `cansupport`.
+    WinnersOnly,
+}
+
+/// Which of several candidates tied for the lowest score is elected.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TieBreak {
+    /// The one appearing first on the ballots.
+    #[default]
+    LowestIndex,
+    /// One drawn uniformly by SplitMix64 seeded from `seed` and the round
+    /// number. The generator is fixed, so the same seed and ballots always
+    /// elect the same winners.
+    Random { seed: u64 },
+    /// The one most preferred under `PhragmenOptions::voter_rankings`: each
+    /// ranking ballot gives its budget to the first tied candidate it both
+    /// ranks and approves, and the highest total wins. Remaining ties go to
+    /// the lowest index.
+    VoterPreference,
+}
+
+/// The load placed on each backer's edge to a newly elected candidate.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum LoadStrategy {
+    /// Sequential Phragmén: every backer's total load is raised to the
+    /// winning score, so the edge takes `score - voterload`.
+    #[default]
+    Equalize,
+    /// Every backer pays `1 / approval` for the seat regardless of the load
+    /// they already carry. Ignores the score, and with it `seat_weight`.
+    Proportional,
+    /// `load(score, voterload, approval)`, with `score` already multiplied
+    /// by `seat_weight`.
+    Custom(fn(f64, f64, f64) -> f64),
+}
+
+impl LoadStrategy {
+    pub fn load(&self, score: f64, voterload: f64, approval: f64) -> f64 {
+        match self {
+            LoadStrategy::Equalize => score - voterload,
+            LoadStrategy::Proportional => 1.0 / approval,
+            LoadStrategy::Custom(load) => load(score, voterload, approval),
+        }
+    }
+}
+
+impl Default for PhragmenOptions {
+    fn default() -> Self {
+        PhragmenOptions {
+            score_bias: HashMap::new(),
+            deterministic: false,
+            seat_weight: 1.0,
+            eps: EPS,
+            support_floor: None,
+            load_strategy: LoadStrategy::Equalize,
+            tie_break: TieBreak::LowestIndex,
+            voter_rankings: HashMap::new(),
+            min_approval: None,
+            min_backers: 0,
+            pools: Vec::new(),
+            include_only: None,
+            exclude: HashSet::new(),
+            compute_weights: true,
+            weight_distribution: WeightDistribution::AllEdges,
+            candidate_group: HashMap::new(),
+            min_per_group: HashMap::new(),
+            approval_mode: false,
+            candidate_quota: HashMap::new(),
+            incremental_rescoring: false,
+            max_support: None,
+            trace: false,
+            record_load_deltas: false,
+            drop_edgeless_voters: false,
+        }
+    }
+}
+
+/// Splits off the ballots that approve no one, returning the remaining
+/// ballots and the dropped voters' ids, both in ballot order.
+///
+/// ```
+/// use seq_phragmen::{drop_edgeless_voters, seq_phragmen_with, PhragmenOptions};
+///
+/// let votelist = vec![
+///     ("A".to_string(), 10.0, vec!["X".to_string(), "Y".to_string()]),
+///     ("B".to_string(), 20.0, vec!["X".to_string()]),
+///     ("D".to_string(), 5.0, vec![]),
+/// ];
+/// let (kept, dropped) = drop_edgeless_voters(votelist.clone());
+/// assert_eq!(kept.len(), 2);
+/// assert_eq!(dropped, vec!["D".to_string()]);
+///
+/// let options = PhragmenOptions {
+///     drop_edgeless_voters: true,
+///     ..Default::default()
+/// };
+/// let dropping = seq_phragmen_with(votelist.clone(), 2, &options);
+/// let keeping = seq_phragmen_with(votelist, 2, &PhragmenOptions::default());
+/// assert_eq!(dropping.voters().len(), 2);
+/// assert_eq!(keeping.voters().len(), 3);
+/// assert_eq!(dropping.dropped_voters(), ["D".to_string()]);
+/// assert!(keeping.dropped_voters().is_empty());
+/// assert_eq!(dropping.approvals(), keeping.approvals());
+/// assert_eq!(dropping.outcome().winners, keeping.outcome().winners);
+/// ```
+pub fn drop_edgeless_voters(votelist: VoteList) -> (VoteList, Vec<String>) {
+    let mut dropped = Vec::new();
+    let kept = votelist
+        .into_iter()
+        .filter_map(|(voterid, budget, approvals)| {
+            if approvals.is_empty() {
+                dropped.push(voterid);
+                None
+            } else {
+                Some((voterid, budget, approvals))
+            }
+        })
+        .collect();
+    (kept, dropped)
+}
+
+/// Drops every approval of a candidate whose total approval is below
+/// `min_approval`. Voters keep their place (and budget) even if all their
+/// approvals are dropped.
+pub fn filter_min_approval(votelist: VoteList, min_approval: f64) -> VoteList {
+    let mut approval: HashMap<String, f64> = HashMap::new();
+    for (_, budget, approvals) in &votelist {
+        for canid in approvals {
+            *approval.entry(canid.clone()).or_insert(0.0) += budget;
+        }
+    }
+    votelist
+        .into_iter()
+        .map(|(voterid, budget, approvals)| {
+            let approvals = approvals
+                .into_iter()
+                .filter(|canid| approval[canid] >= min_approval)
+                .collect();
+            (voterid, budget, approvals)
+        })
+        .collect()
+}
+
+/// Replaces the ballots of each pool's members (by voter id) with one
+/// synthetic voter named after the pool, holding the pool budget and the
+/// union of their approvals in first-seen order. Pools are
+/// `(poolid, budget, member voter ids)`; the synthetic voter takes the
+/// place of the pool's first ballot, or goes last if no member voted. A
+/// voter listed in several pools belongs to the first.
+pub fn merge_pools(votelist: VoteList, pools: &[(String, f64, Vec<String>)]) -> VoteList {
+    let mut poolof: HashMap<&str, usize> = HashMap::new();
+    for (poolindex, (_, _, members)) in pools.iter().enumerate() {
+        for voterid in members {
+            poolof.entry(voterid.as_str()).or_insert(poolindex);
+        }
+    }
+    let mut merged: VoteList = Vec::new();
+    let mut poolposition: Vec<Option<usize>> = vec![None; pools.len()];
+    for (voterid, budget, approvals) in votelist {
+        let Some(&poolindex) = poolof.get(voterid.as_str()) else {
+            merged.push((voterid, budget, approvals));
+            continue;
+        };
+        let position = *poolposition[poolindex].get_or_insert_with(|| {
+            let (poolid, poolbudget, _) = &pools[poolindex];
+            merged.push((poolid.clone(), *poolbudget, Vec::new()));
+            merged.len() - 1
+        });
+        let union = &mut merged[position].2;
+        for canid in approvals {
+            if !union.contains(&canid) {
+                union.push(canid);
+            }
+        }
+    }
+    for (poolindex, (poolid, poolbudget, _)) in pools.iter().enumerate() {
+        if poolposition[poolindex].is_none() {
+            merged.push((poolid.clone(), *poolbudget, Vec::new()));
+        }
+    }
+    merged
+}
+
+/// Keeps only approvals of candidates in `include_only` (all of them when
+/// `None`), then drops approvals of candidates in `exclude`. Voters who lose
+/// every approval this way are removed; voters who approved nobody to begin
+/// with are kept.
+pub fn restrict_candidates(
+    votelist: VoteList,
+    include_only: Option<&HashSet<String>>,
+    exclude: &HashSet<String>,
+) -> VoteList {
+    votelist
+        .into_iter()
+        .filter_map(|(voterid, budget, approvals)| {
+            let hadapprovals = !approvals.is_empty();
+            let approvals: Vec<String> = approvals
+                .into_iter()
+                .filter(|canid| include_only.is_none_or(|include| include.contains(canid)))
+                .filter(|canid| !exclude.contains(canid))
+                .collect();
+            (!approvals.is_empty() || !hadapprovals).then_some((voterid, budget, approvals))
+        })
+        .collect()
+}
+
+/// Puts ballots into a canonical order: voters by id (ties broken by budget,
+/// then approvals) with each voter's approvals sorted by candidate id.
+pub fn sort_votelist(votelist: &mut VoteList) {
+    for votetuple in votelist.iter_mut() {
+        votetuple.2.sort();
+    }
+    votelist.sort_by(|x, y| {
+        x.0.cmp(&y.0)
+            .then(x.1.total_cmp(&y.1))
+            .then_with(|| x.2.cmp(&y.2))
+    });
+}
+
+/// One step of SplitMix64: advances `state` and returns the next output.
+/// Small and fully specified, so seeded draws never change between builds.
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+fn kahan_add(sum: &mut f64, compensation: &mut f64, value: f64) {
+    let y = value - *compensation;
+    let t = *sum + y;
+    *compensation = (t - *sum) - y;
+    *sum = t;
+}
+
+/// Elects up to `numtoelect` candidates by sequential Phragmén. Fewer are
+/// elected when fewer electable candidates exist; ballots without any
+/// approvals give an empty winner set rather than a panic.
+///
+/// # Panics
+///
+/// If there are more than `MAX_INDEX` voters or approvals in total;
+/// `try_seq_phragmen_with` returns an error instead.
+pub fn seq_phragmen<B: Into<Ballot>>(votelist: Vec<B>, numtoelect: usize) -> Assignment {
+    seq_phragmen_with(votelist, numtoelect, &PhragmenOptions::default())
+}
+
+/// `seq_phragmen_with`, but first checks that `min_per_group` can be met:
+/// the minimums together must not exceed `numtoelect`, and each group must
+/// have at least its minimum number of candidates on the ballots. Elections
+/// beyond `MAX_INDEX` are rejected rather than panicking.
+pub fn try_seq_phragmen_with<B: Into<Ballot>>(
+    votelist: Vec<B>,
+    numtoelect: usize,
+    options: &PhragmenOptions,
+) -> Result<Assignment, PhragmenError> {
+    let votelist = into_votelist(votelist);
+    check_index_limits(votelist.len(), votelist.iter().map(|v| v.2.len()).sum())?;
+    let required: usize = options.min_per_group.values().sum();
+    if required > numtoelect {
+        return Err(PhragmenError::GroupMinimumsExceedSeats {
+            required,
+            seats: numtoelect,
+        });
+    }
+    let canids: HashSet<&String> = votelist.iter().flat_map(|v| &v.2).collect();
+    for (group, &minimum) in &options.min_per_group {
+        let available = canids
+            .iter()
+            .filter(|canid| options.candidate_group.get(**canid) == Some(group))
+            .count();
+        if available < minimum {
+            return Err(PhragmenError::GroupMinimumUnreachable {
+                group: group.clone(),
+                required: minimum,
+                available,
+            });
+        }
+    }
+    Ok(seq_phragmen_with(votelist, numtoelect, options))
+}
+
+/// `seq_phragmen`, stopping between seat rounds once `max` has elapsed.
+/// Returns the assignment with the seats filled so far (weights computed as
+/// usual) and whether the limit cut the election short. At least the
+/// setup always runs, and a round in progress is never interrupted, so the
+/// call can overrun `max` by one round.
+pub fn seq_phragmen_timed_limit<B: Into<Ballot>>(
+    votelist: Vec<B>,
+    numtoelect: usize,
+    max: std::time::Duration,
+) -> (Assignment, bool) {
+    let start = std::time::Instant::now();
+    let options = PhragmenOptions::default();
+    let (nomlist, candidates) = setuplists(votelist);
+    let mut a = Assignment::new(nomlist, candidates, None);
+    let mut timed_out = false;
+    for _ in 0..numtoelect {
+        if start.elapsed() >= max {
+            timed_out = true;
+            break;
+        }
+        if a.elect_next(&options).is_none() {
+            break;
+        }
+    }
+    a.loadstoweights_eps(options.eps);
+    (a, timed_out)
+}
+
+/// Which candidates may be elected next under `min_per_group`, or `None`
+/// when there is still room for any candidate.
+fn reserved_groups_only(
+    a: &Assignment,
+    options: &PhragmenOptions,
+    seatsleft: usize,
+) -> Option<Vec<bool>> {
+    if options.min_per_group.is_empty() {
+        return None;
+    }
+    let mut won: HashMap<&str, usize> = HashMap::new();
+    for &index in &a.electionorder {
+        if let Some(group) = options.candidate_group.get(&a.candidates[index].canid) {
+            *won.entry(group.as_str()).or_insert(0) += 1;
+        }
+    }
+    let short = |group: &str| {
+        options
+            .min_per_group
+            .get(group)
+            .is_some_and(|&minimum| won.get(group).copied().unwrap_or(0) < minimum)
+    };
+    let deficit: usize = options
+        .min_per_group
+        .iter()
+        .map(|(group, &minimum)| {
+            minimum.saturating_sub(won.get(group.as_str()).copied().unwrap_or(0))
+        })
+        .sum();
+    if deficit == 0 || deficit < seatsleft {
+        return None;
+    }
+    Some(
+        a.candidates
+            .iter()
+            .map(|candidate| {
+                options
+                    .candidate_group
+                    .get(&candidate.canid)
+                    .is_some_and(|group| short(group))
+            })
+            .collect(),
+    )
+}
+
+pub fn seq_phragmen_with<B: Into<Ballot>>(
+    votelist: Vec<B>,
+    numtoelect: usize,
+    options: &PhragmenOptions,
+) -> Assignment {
+    let mut votelist = into_votelist(votelist);
+    if !options.pools.is_empty() {
+        votelist = merge_pools(votelist, &options.pools);
+    }
+    if options.approval_mode {
+        for ballot in &mut votelist {
+            ballot.1 = 1.0;
+        }
+    }
+    if options.include_only.is_some() || !options.exclude.is_empty() {
+        votelist = restrict_candidates(votelist, options.include_only.as_ref(), &options.exclude);
+    }
+    if let Some(min_approval) = options.min_approval {
+        votelist = filter_min_approval(votelist, min_approval);
+    }
+    let mut dropped = Vec::new();
+    if options.drop_edgeless_voters {
+        (votelist, dropped) = drop_edgeless_voters(votelist);
+    }
+    if options.deterministic {
+        sort_votelist(&mut votelist);
+    }
+    let voter_hint = votelist.len();
+    let candidate_hint = votelist
+        .iter()
+        .flat_map(|v| &v.2)
+        .collect::<HashSet<_>>()
+        .len();
+    let (nomlist, candidates) = setuplists_with_capacity(votelist, candidate_hint, voter_hint);
+    let mut a = Assignment::new(nomlist, candidates, None);
+    a.droppedvoters = dropped;
+    a.eps = options.eps;
+    if a.candidates.is_empty() {
+        return a;
+    }
+
+    for round in 0..numtoelect {
+        let allowed = reserved_groups_only(&a, options, numtoelect - round);
+        if a.elect_next_among(options, allowed.as_deref()).is_none() {
+            break;
+        }
+    }
+    if options.compute_weights {
+        a.ensure_weights(options);
+    }
+    a
+}
+
+/// How strongly a graded ballot approves a candidate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Grade {
+    Strong,
+    Weak,
+}
+
+impl Grade {
+    /// The share of the voter's budget the approval counts with.
+    pub fn fraction(self) -> f64 {
+        match self {
+            Grade::Strong => 1.0,
+            Grade::Weak => 0.5,
+        }
+    }
+}
+
+/// Sequential Phragmén over graded ballots. An approval graded `g` counts
+/// `budget * g.fraction()` towards the candidate's approval and score, and
+/// once the winners are fixed each voter's budget is spread over their
+/// edges in proportion to `fraction * load`, so support leans towards the
+/// strongly graded winners. Loads equalize as in a plain run. With every
+/// approval `Strong` this is `seq_phragmen`. A candidate graded twice on a
+/// ballot takes the first grade for both edges.
+///
+/// ```
+/// use seq_phragmen::{seq_phragmen, seq_phragmen_graded, Grade};
+///
+/// let graded = |x: Grade, y: Grade| {
+///     vec![
+///         ("A".to_string(), 10.0, vec![("X".to_string(), x), ("Y".to_string(), y)]),
+///         ("B".to_string(), 20.0, vec![("X".to_string(), x), ("Z".to_string(), Grade::Strong)]),
+///         ("C".to_string(), 30.0, vec![("Y".to_string(), Grade::Strong), ("Z".to_string(), Grade::Strong)]),
+///     ]
+/// };
+/// let plain = vec![
+///     ("A".to_string(), 10.0, vec!["X".to_string(), "Y".to_string()]),
+///     ("B".to_string(), 20.0, vec!["X".to_string(), "Z".to_string()]),
+///     ("C".to_string(), 30.0, vec!["Y".to_string(), "Z".to_string()]),
+/// ];
+/// let strong = seq_phragmen_graded(graded(Grade::Strong, Grade::Strong), 3);
+/// assert_eq!(strong.outcome(), seq_phragmen(plain, 3).outcome());
+///
+/// let mixed = seq_phragmen_graded(graded(Grade::Strong, Grade::Weak), 3);
+/// let weight_on = |a: &seq_phragmen::Assignment, canid: &str| {
+///     a.outcome().assignments[0].1.iter().find(|(c, _)| c == canid).unwrap().1
+/// };
+/// assert!(weight_on(&mixed, "X") > weight_on(&strong, "X"));
+/// assert!(weight_on(&mixed, "Y") < weight_on(&strong, "Y"));
+/// ```
+pub fn seq_phragmen_graded(votelist: GradedVoteList, numtoelect: usize) -> Assignment {
+    let mut grades: Vec<HashMap<String, f64>> = Vec::with_capacity(votelist.len());
+    let votelist: VoteList = votelist
+        .into_iter()
+        .map(|(voterid, budget, graded)| {
+            let mut fractions = HashMap::with_capacity(graded.len());
+            let approvals = graded
+                .into_iter()
+                .map(|(canid, grade)| {
+                    fractions.entry(canid.clone()).or_insert(grade.fraction());
+                    canid
+                })
+                .collect();
+            grades.push(fractions);
+            (voterid, budget, approvals)
+        })
+        .collect();
+    let (nomlist, candidates) = setuplists(votelist);
+    let numedges = nomlist.iter().map(|nom| nom.edges.len()).sum();
+    let mut edgefraction = vec![1.0; numedges];
+    for nom in &nomlist {
+        for edge in &nom.edges {
+            edgefraction[edge.index()] = grades[nom.index][&candidates[edge.canindex()].canid];
+        }
+    }
+    let mut a = Assignment::new(nomlist, candidates, None);
+    a.edgefraction = edgefraction;
+    a.recompute_approval();
+    let options = PhragmenOptions::default();
+    for _ in 0..numtoelect {
+        if a.elect_next_among(&options, None).is_none() {
+            break;
+        }
+    }
+    a.loadstoweights();
+    a
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DivisorKind {
+    /// Divisors 1, 2, 3, ...
+    DHondt,
+    /// Divisors 1, 3, 5, ...
+    SainteLague,
+}
+
+impl DivisorKind {
+    fn divisor(self, seats_won: usize) -> f64 {
+        match self {
+            DivisorKind::DHondt => (seats_won + 1) as f64,
+            DivisorKind::SainteLague => (2 * seats_won + 1) as f64,
+        }
+    }
+}
+
+/// Approval-based divisor method for comparison with `seq_phragmen`. Each
+/// round elects the candidate with the highest approval, where a voter's
+/// budget counts as `budget / divisor(seats_won)` and `seats_won` is the
+/// number of already elected candidates that voter approves of. With
+/// `DHondt` this is reweighted approval voting. Only the winner set is
+/// filled in; loads and weights stay at zero.
+pub fn divisor_method<B: Into<Ballot>>(
+    votelist: Vec<B>,
+    numtoelect: usize,
+    divisor: DivisorKind,
+) -> Assignment {
+    let (nomlist, candidates) = setuplists(votelist);
+    let mut a = Assignment::new(nomlist, candidates, None);
+    let mut seatswon = vec![0; a.voterlist.len()];
+
+    for _ in 0..numtoelect.min(a.candidates.len()) {
+        let mut approval = vec![0.0; a.candidates.len()];
+        for nom in &a.voterlist {
+            let share = nom.budget / divisor.divisor(seatswon[nom.index]);
+            for edge in &nom.edges {
+                approval[edge.canindex()] += share;
+            }
+        }
+        let mut bestcandidate = None;
+        let mut bestapproval = f64::NEG_INFINITY;
+        for (canindex, &canapproval) in approval.iter().enumerate() {
+            if !a.is_elected(canindex) && canapproval > bestapproval {
+                bestapproval = canapproval;
+                bestcandidate = Some(canindex);
+            }
+        }
+        let Some(bestcandidate) = bestcandidate else {
+            break;
+        };
+        let electedcandidate = a.candidates[bestcandidate].clone();
+        a.elect(&electedcandidate);
+        for nom in &a.voterlist {
+            if nom
+                .edges
+                .iter()
+                .any(|edge| edge.canindex() == bestcandidate)
+            {
+                seatswon[nom.index] += 1;
+            }
+        }
+    }
+    a
+}
+
+/// Groups candidate ids into the connected components of the voter–candidate
+/// approval graph: two candidates share a component when a chain of voters
+/// approving both links them. Candidates in different components never
+/// influence each other's scores. Components are ordered by the first
+/// appearance of any of their candidates in `votelist`.
+pub fn connected_components(votelist: &[(String, f64, Vec<String>)]) -> Vec<HashSet<String>> {
+    fn find(parent: &mut [usize], index: usize) -> usize {
+        let mut root = index;
+        while parent[root] != root {
+            root = parent[root];
+        }
+        let mut index = index;
+        while parent[index] != root {
+            let next = parent[index];
+            parent[index] = root;
+            index = next;
+        }
+        root
+    }
+
+    let mut candidatedict: HashMap<&str, usize> = HashMap::new();
+    let mut canids: Vec<&str> = Vec::new();
+    let mut parent: Vec<usize> = Vec::new();
+    for (_, _, approvals) in votelist {
+        let mut first = None;
+        for canid in approvals {
+            let canindex = *candidatedict.entry(canid.as_str()).or_insert_with(|| {
+                canids.push(canid.as_str());
+                parent.push(parent.len());
+                parent.len() - 1
+            });
+            match first {
+                None => first = Some(canindex),
+                Some(first) => {
+                    let x = find(&mut parent, first);
+                    let y = find(&mut parent, canindex);
+                    if x != y {
+                        parent[y.max(x)] = y.min(x);
+                    }
+                }
+            }
+        }
+    }
+
+    let mut componentindex: HashMap<usize, usize> = HashMap::new();
+    let mut components: Vec<HashSet<String>> = Vec::new();
+    for (canindex, canid) in canids.iter().enumerate() {
+        let root = find(&mut parent, canindex);
+        let component = *componentindex.entry(root).or_insert_with(|| {
+            components.push(HashSet::new());
+            components.len() - 1
+        });
+        components[component].insert(canid.to_string());
+    }
+    components
+}
+
+/// Renders ballots as a compilable `vec![...]` expression in the layout of
+/// the sample in `main`, for pasting into a test. Budgets are printed so that
+/// they parse back to the identical `f64`.
+pub fn votelist_to_rust_literal(votes: &[(String, f64, Vec<String>)]) -> String {
+    let mut literal = String::from("vec![\n");
+    for (voterid, budget, approvals) in votes {
+        let budget = if budget.is_nan() {
+            "f64::NAN".to_string()
+        } else if budget.is_infinite() {
+            if *budget > 0.0 {
+                "f64::INFINITY".to_string()
+            } else {
+                "f64::NEG_INFINITY".to_string()
+            }
+        } else {
+            format!("{budget:?}")
+        };
+        let approvals: Vec<String> = approvals
+            .iter()
+            .map(|canid| format!("{canid:?}.to_string()"))
+            .collect();
+        literal.push_str("    (\n");
+        literal.push_str(&format!("        {voterid:?}.to_string(),\n"));
+        literal.push_str(&format!("        {budget},\n"));
+        literal.push_str(&format!("        vec![{}],\n", approvals.join(", ")));
+        literal.push_str("    ),\n");
+    }
+    literal.push(']');
+    literal
+}
+
+/// Weighted Jaccard similarity of two outcomes' `(voter, candidate)` weights:
+/// the sum of the smaller weight over the sum of the larger weight, taken
+/// over every pair present in either outcome (a missing pair has weight 0).
+/// Weights of repeated voter ids are added together, and negative weights
+/// count as 0. The result lies in `[0, 1]`: 1.0 for identical assignments
+/// (including two empty ones) and 0.0 when no pair carries weight in both.
+pub fn assignment_similarity(a: &Outcome, b: &Outcome) -> f64 {
+    fn weights(outcome: &Outcome) -> HashMap<(&str, &str), f64> {
+        let mut weights = HashMap::new();
+        for (voterid, edges) in &outcome.assignments {
+            for (canid, weight) in edges {
+                *weights
+                    .entry((voterid.as_str(), canid.as_str()))
+                    .or_insert(0.0) += weight.max(0.0);
+            }
+        }
+        weights
+    }
+
+    let aweights = weights(a);
+    let bweights = weights(b);
+    let mut intersection = 0.0;
+    let mut union = 0.0;
+    for (key, &aweight) in &aweights {
+        let bweight = bweights.get(key).copied().unwrap_or(0.0);
+        intersection += aweight.min(bweight);
+        union += aweight.max(bweight);
+    }
+    for (key, &bweight) in &bweights {
+        if !aweights.contains_key(key) {
+            union += bweight;
+        }
+    }
+    if union > 0.0 {
+        intersection / union
+    } else {
+        1.0
+    }
+}
+
+/// Elects `batch_sizes[0]` seats, then `batch_sizes[1]`, and so on, calling
+/// `between` after every batch but the last with the outcome so far and the
+/// voters' budgets (indexed like the input). Budget changes made by the
+/// callback apply to all later batches. With a callback that changes
+/// nothing the result equals `seq_phragmen` for the total number of seats.
+pub fn seq_phragmen_batched<B: Into<Ballot>>(
+    votelist: Vec<B>,
+    batch_sizes: Vec<usize>,
+    mut between: impl FnMut(&Outcome, &mut [f64]),
+) -> Assignment {
+    let (nomlist, candidates) = setuplists(votelist);
+    let mut a = Assignment::new(nomlist, candidates, None);
+    let options = PhragmenOptions::default();
+
+    for (batch, &batch_size) in batch_sizes.iter().enumerate() {
+        for _ in 0..batch_size {
+            if a.elect_next(&options).is_none() {
+                break;
+            }
+        }
+        if batch + 1 < batch_sizes.len() {
+            // Weights are computed on a copy: setting them on `a` now and
+            // again at the end would drift the supports by rounding.
+            let mut snapshot = a.clone();
+            snapshot.loadstoweights();
+            let mut budgets: Vec<f64> = a.voterlist.iter().map(|voter| voter.budget).collect();
+            between(&snapshot.outcome(), &mut budgets);
+            a.set_budgets(&budgets);
+        }
+    }
+    a.loadstoweights();
+    a
+}
+
+/// Reruns the election once per scale factor with the budget of every ballot
+/// cast under `voterid` multiplied by that factor, returning each factor with
+/// the sorted ids of the resulting winners. At scale 0.0 those ballots carry
+/// no weight at all; at 1.0 the result is the unmodified election.
+pub fn sensitivity<B: Into<Ballot>>(
+    votelist: Vec<B>,
+    numtoelect: usize,
+    voterid: &str,
+    scales: &[f64],
+) -> Vec<(f64, Vec<String>)> {
+    let votelist = into_votelist(votelist);
+    scales
+        .iter()
+        .map(|&scale| {
+            let scaled = votelist
+                .iter()
+                .map(|(id, budget, approvals)| {
+                    let budget = if id == voterid {
+                        budget * scale
+                    } else {
+                        *budget
+                    };
+                    (id.clone(), budget, approvals.clone())
+                })
+                .collect();
+            let a = seq_phragmen(scaled, numtoelect);
+            let mut winners: Vec<String> = a
+                .electedcandidates
+                .iter()
+                .map(|&index| a.candidates[index].canid.clone())
+                .collect();
+            winners.sort();
+            (scale, winners)
+        })
+        .collect()
+}
+
+/// For every candidate on the ballots, the sorted ids of the winners when
+/// that candidate is left out through `PhragmenOptions::exclude`. Costs one
+/// full rerun per candidate.
+pub fn candidate_impact<B: Into<Ballot>>(
+    votelist: Vec<B>,
+    numtoelect: usize,
+) -> HashMap<String, Vec<String>> {
+    let votelist = into_votelist(votelist);
+    let canids: HashSet<String> = votelist.iter().flat_map(|v| v.2.iter().cloned()).collect();
+    canids
+        .into_iter()
+        .map(|canid| {
+            let options = PhragmenOptions {
+                exclude: HashSet::from([canid.clone()]),
+                ..PhragmenOptions::default()
+            };
+            let a = seq_phragmen_with(votelist.clone(), numtoelect, &options);
+            let mut winners: Vec<String> = a
+                .electedcandidates
+                .iter()
+                .map(|&index| a.candidates[index].canid.clone())
+                .collect();
+            winners.sort();
+            (canid, winners)
+        })
+        .collect()
+}
+
+/// Runs `seq_phragmen` and writes one JSON object per line to `writer` as
+/// each seat is filled: the round (from 1), the elected candidate id, its
+/// winning score and the support of every candidate elected so far, as if
+/// the election stopped after that round.
+#[cfg(feature = "serde")]
+pub fn seq_phragmen_ndjson<B: Into<Ballot>, W: std::io::Write>(
+    votelist: Vec<B>,
+    numtoelect: usize,
+    mut writer: W,
+) -> std::io::Result<Assignment> {
+    let options = PhragmenOptions::default();
+    let (nomlist, candidates) = setuplists(votelist);
+    let mut a = Assignment::new(nomlist, candidates, None);
+
+    for round in 1..=numtoelect {
+        let Some(elected) = a.elect_next(&options) else {
+            break;
+        };
+        a.loadstoweights_eps(options.eps);
+        let supports: serde_json::Map<String, serde_json::Value> = a
+            .electionorder
+            .iter()
+            .map(|&index| {
+                (
+                    a.candidates[index].canid.clone(),
+                    a.cansupport[index].into(),
+                )
+            })
+            .collect();
+        let line = serde_json::json!({
+            "round": round,
+            "elected": a.candidates[elected].canid,
+            "score": a.canscore[elected],
+            "supports": supports,
+        });
+        serde_json::to_writer(&mut writer, &line)?;
+        writer.write_all(b"\n")?;
+    }
+    a.loadstoweights_eps(options.eps);
+    Ok(a)
+}
+
+/// Per group, `(budget_share, support_share)`: the group's share of the total
+/// budget and its share of the total weight given to winners. Voters are
+/// looked up by id in `group_of`; voters without a group are left out of both
+/// totals, so each kind of share sums to 1.0 over the groups (when the
+/// totals are positive).
+pub fn group_representation(
+    outcome: &Outcome,
+    group_of: &HashMap<String, String>,
+) -> HashMap<String, (f64, f64)> {
+    let mut totals: HashMap<String, (f64, f64)> = HashMap::new();
+    let mut totalbudget = 0.0;
+    let mut totalsupport = 0.0;
+    for ((voterid, edges), &budget) in outcome.assignments.iter().zip(&outcome.budgets) {
+        let Some(group) = group_of.get(voterid) else {
+            continue;
+        };
+        let support: f64 = edges.iter().map(|(_, weight)| weight).sum();
+        let entry = totals.entry(group.clone()).or_insert((0.0, 0.0));
+        entry.0 += budget;
+        entry.1 += support;
+        totalbudget += budget;
+        totalsupport += support;
+    }
+    for (budget, support) in totals.values_mut() {
+        *budget = if totalbudget > 0.0 {
+            *budget / totalbudget
+        } else {
+            0.0
+        };
+        *support = if totalsupport > 0.0 {
+            *support / totalsupport
+        } else {
+            0.0
+        };
+    }
+    totals
+}
+
+/// The complete sequential Phragmén ranking: every candidate with the round
+/// (from 1) in which it would be elected if all candidates were seated.
+/// Candidates that can never be elected (no positive approval) follow the
+/// others in order of first appearance.
+pub fn seq_phragmen_full<B: Into<Ballot>>(votelist: Vec<B>) -> Vec<(String, usize)> {
+    let options = PhragmenOptions::default();
+    let (nomlist, candidates) = setuplists(votelist);
+    let mut a = Assignment::new(nomlist, candidates, None);
+    let mut ranking = Vec::with_capacity(a.candidates.len());
+    while let Some(elected) = a.elect_next(&options) {
+        ranking.push((a.candidates[elected].canid.clone(), ranking.len() + 1));
+    }
+    for candidate in &a.candidates {
+        if !a.is_elected(candidate.index) {
+            ranking.push((candidate.canid.clone(), ranking.len() + 1));
+        }
+    }
+    ranking
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct BudgetStats {
+    pub min: f64,
+    pub max: f64,
+    pub median: f64,
+    pub p90: f64,
+    /// Gini coefficient of the budgets: 0.0 when all are equal, approaching
+    /// 1.0 as budget concentrates in a single voter.
+    pub gini: f64,
+}
+
+/// Summary statistics of the voters' budgets, for sanity-checking input.
+/// Percentiles interpolate linearly between the closest ranks. All fields
+/// are 0.0 for an empty `votelist`.
+pub fn budget_stats(votelist: &[(String, f64, Vec<String>)]) -> BudgetStats {
+    let mut budgets: Vec<f64> = votelist.iter().map(|(_, budget, _)| *budget).collect();
+    if budgets.is_empty() {
+        return BudgetStats::default();
+    }
+    budgets.sort_by(f64::total_cmp);
+    let percentile = |p: f64| {
+        let rank = p * (budgets.len() - 1) as f64;
+        let lower = rank.floor() as usize;
+        let upper = rank.ceil() as usize;
+        budgets[lower] + (budgets[upper] - budgets[lower]) * (rank - lower as f64)
+    };
+    let n = budgets.len() as f64;
+    let total: f64 = budgets.iter().sum();
+    let gini = if total > 0.0 {
+        let weighted: f64 = budgets
+            .iter()
+            .enumerate()
+            .map(|(i, budget)| (2.0 * (i + 1) as f64 - n - 1.0) * budget)
+            .sum();
+        weighted / (n * total)
+    } else {
+        0.0
+    };
+    BudgetStats {
+        min: budgets[0],
+        max: budgets[budgets.len() - 1],
+        median: percentile(0.5),
+        p90: percentile(0.9),
+        gini,
+    }
+}
+
+/// Total approval per candidate id in one pass over `votes`, without
+/// building voters, edges or an assignment; only the running totals are
+/// kept. Matches `Assignment::approvals` for the same ballots, including
+/// counting a repeated approval twice.
+pub fn stream_approval<I: Iterator<Item = (String, f64, Vec<String>)>>(
+    votes: I,
+) -> HashMap<String, f64> {
+    let mut approval: HashMap<String, f64> = HashMap::new();
+    for (_, budget, approvals) in votes {
+        for canid in approvals {
+            *approval.entry(canid).or_insert(0.0) += budget;
+        }
+    }
+    approval
+}
+
+/// Who would take seat `current_seats + 1`, and the minimum support of the
+/// enlarged council. Reruns the election with one more seat; `None` if there
+/// is no candidate left to take it.
+pub fn marginal_seat<B: Into<Ballot>>(
+    votelist: Vec<B>,
+    current_seats: usize,
+) -> Option<(String, f64)> {
+    let a = seq_phragmen(votelist, current_seats + 1);
+    if a.electionorder.len() <= current_seats {
+        return None;
+    }
+    let last = *a.electionorder.last()?;
+    Some((a.candidates[last].canid.clone(), a.election_score().0))
+}
+
+/// Checks `winners` against the core by single replacements. Each seat
+/// costs a quota of `1 / numtoelect` of the total budget. For a winner `w`
+/// and a non-winner `c`, the voters approving `c` but not `w` are the ones
+/// who strictly prefer the winner set with `c` in place of `w`. They block
+/// `winners` if their combined budget pays a quota for `c` and one for every
+/// winner any of them approves: funded from their own budgets, each of them
+/// then has one more approved winner than now, so even the worst-off member
+/// gains. A coalition approving no winner at all (a justified
+/// representation failure) is checked first, as it needs only `c`'s quota.
+/// Returns whether no such coalition exists. This is a necessary condition
+/// for core membership, not a full core check, which would also consider
+/// coalitions buying several new seats. Budgets are validated as in
+/// `VoteSet`; shares get a relative tolerance of `1e-9`.
+pub fn is_in_core(
+    votelist: &[(String, f64, Vec<String>)],
+    winners: &[String],
+    numtoelect: usize,
+) -> Result<bool, PhragmenError> {
+    for (index, (voterid, budget, _)) in votelist.iter().enumerate() {
+        check_budget(index, voterid, *budget)?;
+    }
+    if numtoelect == 0 {
+        return Ok(true);
+    }
+    let totalbudget: f64 = votelist.iter().map(|v| v.1).sum();
+    let quota = totalbudget / numtoelect as f64;
+    let affords = |budget: f64, seats: usize| budget >= quota * seats as f64 * (1.0 - 1e-9);
+    let mut unrepresented: HashMap<&str, f64> = HashMap::new();
+    for (_, budget, approvals) in votelist {
+        if approvals.iter().any(|canid| winners.contains(canid)) {
+            continue;
+        }
+        let distinct: HashSet<&String> = approvals.iter().collect();
+        for canid in distinct {
+            *unrepresented.entry(canid.as_str()).or_insert(0.0) += budget;
+        }
+    }
+    if unrepresented.values().any(|&budget| affords(budget, 1)) {
+        return Ok(false);
+    }
+    let winnerset: HashSet<&str> = winners.iter().map(String::as_str).collect();
+    let challengers: HashSet<&str> = votelist
+        .iter()
+        .flat_map(|v| v.2.iter().map(String::as_str))
+        .filter(|canid| !winnerset.contains(canid))
+        .collect();
+    for &challenger in &challengers {
+        for &replaced in &winnerset {
+            let mut budget = 0.0;
+            let mut seats: HashSet<&str> = HashSet::from([challenger]);
+            for (_, ballotbudget, approvals) in votelist {
+                let approves = |canid: &str| approvals.iter().any(|approved| approved == canid);
+                if approves(challenger) && !approves(replaced) {
+                    budget += ballotbudget;
+                    seats.extend(
+                        approvals
+                            .iter()
+                            .map(String::as_str)
+                            .filter(|canid| winnerset.contains(canid)),
+                    );
+                }
+            }
+            if affords(budget, seats.len()) {
+                return Ok(false);
+            }
+        }
+    }
+    Ok(true)
+}
+
+/// The number of seats the voters in `faction` are guaranteed whatever the
+/// other ballots say: `floor(faction_budget / total_budget * numtoelect)`,
+/// capped by the number of candidates every faction ballot approves. This
+/// is the proportional justified representation bound sequential Phragmén
+/// meets, so it assumes the faction is cohesive, votes sincerely for the
+/// shared candidates, and that those candidates stay electable; a faction
+/// sharing no candidate is guaranteed nothing. Every ballot whose voter id
+/// is in `faction` counts towards it, and the share is given a relative
+/// tolerance of `1e-9`, as in `is_in_core`.
+///
+/// ```
+/// use seq_phragmen::guaranteed_seats;
+///
+/// let votelist = vec![
+///     ("A".to_string(), 10.0, vec!["X".to_string(), "Y".to_string()]),
+///     ("B".to_string(), 10.0, vec!["X".to_string()]),
+///     ("C".to_string(), 40.0, vec!["Z".to_string(), "W".to_string(), "V".to_string()]),
+/// ];
+/// let faction = ["A".to_string(), "B".to_string()];
+/// assert_eq!(guaranteed_seats(&votelist, 3, &faction), 1);
+/// ```
+pub fn guaranteed_seats(
+    votelist: &[(String, f64, Vec<String>)],
+    numtoelect: usize,
+    faction: &[String],
+) -> usize {
+    let totalbudget: f64 = votelist.iter().map(|v| v.1).sum();
+    let mut factionbudget = 0.0;
+    let mut shared: Option<HashSet<&String>> = None;
+    for (voterid, budget, approvals) in votelist {
+        if !faction.contains(voterid) {
+            continue;
+        }
+        factionbudget += budget;
+        let approved: HashSet<&String> = approvals.iter().collect();
+        shared = Some(match shared {
+            Some(shared) => shared.intersection(&approved).copied().collect(),
+            None => approved,
+        });
+    }
+    let Some(shared) = shared else {
+        return 0;
+    };
+    if totalbudget <= 0.0 {
+        return 0;
+    }
+    let share = factionbudget / totalbudget * numtoelect as f64;
+    let seats = (share * (1.0 + 1e-9)).floor() as usize;
+    seats.min(shared.len()).min(numtoelect)
+}
+
+/// Checks a claimed solution against the ballots without rerunning the
+/// election: every winner appears on the ballots exactly once, every
+/// assigned weight is non-negative and goes from a voter to a winner that
+/// voter approves, and no voter assigns more than their budget (up to a
+/// relative tolerance of `1e-9`). Ballots and assignments under the same
+/// voter id are pooled. On success returns the minimum support over the
+/// winners implied by the assignments (0.0 if there are no winners); this
+/// is the number to compare competing solutions by.
+pub fn verify_solution(
+    votelist: &[(String, f64, Vec<String>)],
+    winners: &[String],
+    assignments: &[(String, Vec<(String, f64)>)],
+) -> Result<f64, VerificationError> {
+    let mut budgets: HashMap<&str, f64> = HashMap::new();
+    let mut approved: HashSet<(&str, &str)> = HashSet::new();
+    for (voterid, budget, approvals) in votelist {
+        *budgets.entry(voterid.as_str()).or_insert(0.0) += budget;
+        for canid in approvals {
+            approved.insert((voterid.as_str(), canid.as_str()));
+        }
+    }
+    let oncandidates: HashSet<&str> = approved.iter().map(|&(_, canid)| canid).collect();
+
+    let mut support: HashMap<&str, f64> = HashMap::new();
+    for canid in winners {
+        if !oncandidates.contains(canid.as_str()) {
+            return Err(VerificationError::UnknownWinner {
+                canid: canid.clone(),
+            });
+        }
+        if support.insert(canid.as_str(), 0.0).is_some() {
+            return Err(VerificationError::DuplicateWinner {
+                canid: canid.clone(),
+            });
+        }
+    }
+
+    let mut assigned: HashMap<&str, f64> = HashMap::new();
+    for (voterid, edges) in assignments {
+        if !budgets.contains_key(voterid.as_str()) {
+            return Err(VerificationError::UnknownVoter {
+                voterid: voterid.clone(),
+            });
+        }
+        for (canid, weight) in edges {
+            let Some(winnersupport) = support.get_mut(canid.as_str()) else {
+                return Err(VerificationError::NotAWinner {
+                    voterid: voterid.clone(),
+                    canid: canid.clone(),
+                });
+            };
+            if !approved.contains(&(voterid.as_str(), canid.as_str())) {
+                return Err(VerificationError::NotApproved {
+                    voterid: voterid.clone(),
+                    canid: canid.clone(),
+                });
+            }
+            if *weight < 0.0 || weight.is_nan() {
+                return Err(VerificationError::NegativeWeight {
+                    voterid: voterid.clone(),
+                    canid: canid.clone(),
+                    weight: *weight,
+                });
+            }
+            *winnersupport += weight;
+            *assigned.entry(voterid.as_str()).or_insert(0.0) += weight;
+        }
+    }
+    for (voterid, assigned) in assigned {
+        let budget = budgets[voterid];
+        if assigned > budget + budget.abs() * 1e-9 {
+            return Err(VerificationError::BudgetExceeded {
+                voterid: voterid.to_string(),
+                budget,
+                assigned,
+            });
+        }
+    }
+
+    Ok(support.values().copied().reduce(f64::min).unwrap_or(0.0))
+}
+
+/// The smallest extra budget that gets `new_candidate` elected, to within a
+/// relative `1e-9`. The budget is added as new ballots, split evenly over
+/// `backers` (voter ids, existing or not), each approving only
+/// `new_candidate`; every probe reruns the election. Returns infinity when no
+/// finite budget suffices (e.g. no backers or no seats).
+pub fn entry_threshold<B: Into<Ballot>>(
+    votelist: Vec<B>,
+    numtoelect: usize,
+    new_candidate: &str,
+    backers: &[String],
+) -> f64 {
+    let votelist = into_votelist(votelist);
+    let wins = |budget: f64| {
+        let share = budget / backers.len() as f64;
+        let mut votelist = votelist.clone();
+        votelist.extend(
+            backers
+                .iter()
+                .map(|voterid| (voterid.clone(), share, vec![new_candidate.to_string()])),
+        );
+        let a = seq_phragmen(votelist, numtoelect);
+        a.candidate_by_id(new_candidate)
+            .is_some_and(|candidate| a.is_elected(candidate.index))
+    };
+    if backers.is_empty() || numtoelect == 0 {
+        return f64::INFINITY;
+    }
+
+    let total: f64 = votelist.iter().map(|(_, budget, _)| budget).sum();
+    let mut high = total.max(1.0);
+    let mut doublings = 0;
+    while !wins(high) {
+        high *= 2.0;
+        doublings += 1;
+        if doublings > 64 || !high.is_finite() {
+            return f64::INFINITY;
+        }
+    }
+    let mut low = 0.0;
+    while high - low > high * 1e-9 {
+        let mid = (low + high) / 2.0;
+        if wins(mid) {
+            high = mid;
+        } else {
+            low = mid;
+        }
+    }
+    high
+}
+
+/// The first-round score (`1 / approval`) of every candidate, ascending with
+/// ties broken by id, without electing anyone.
+pub fn initial_scores<B: Into<Ballot>>(votelist: Vec<B>) -> Vec<(String, f64)> {
+    let (nomlist, candidates) = setuplists(votelist);
+    let mut a = Assignment::new(nomlist, candidates, None);
+    a.recompute_scores(&PhragmenOptions::default());
+    let mut scores: Vec<(String, f64)> = a
+        .candidates
+        .iter()
+        .map(|candidate| (candidate.canid.clone(), a.canscore[candidate.index]))
+        .collect();
+    scores.sort_by(|x, y| x.1.total_cmp(&y.1).then_with(|| x.0.cmp(&y.0)));
+    scores
+}
+
+/// Sequential Phragmén in exact rational arithmetic, free of any float
+/// rounding or ordering effects. Returns the winners in election order with
+/// their exact support. Candidates without positive approval are never
+/// elected; ties go to the candidate appearing first on the ballots.
+#[cfg(feature = "rational")]
+pub fn seq_phragmen_rational(
+    votelist: Vec<(String, num_rational::BigRational, Vec<String>)>,
+    numtoelect: usize,
+) -> Vec<(String, num_rational::BigRational)> {
+    use num_rational::BigRational;
+    use num_traits::{One, Signed, Zero};
+
+    let budgets: Vec<BigRational> = votelist.iter().map(|v| v.1.clone()).collect();
+    let (nomlist, candidates) = setuplists(
+        votelist
+            .into_iter()
+            .map(|(voterid, _, approvals)| (voterid, 0.0, approvals))
+            .collect(),
+    );
+    let mut approval = vec![BigRational::zero(); candidates.len()];
+    for nom in &nomlist {
+        for edge in &nom.edges {
+            approval[edge.canindex()] += &budgets[nom.index];
+        }
+    }
+    let numedges: usize = nomlist.iter().map(|nom| nom.edges.len()).sum();
+    let mut voterload = vec![BigRational::zero(); nomlist.len()];
+    let mut edgeload = vec![BigRational::zero(); numedges];
+    let mut elected = vec![false; candidates.len()];
+    let mut electionorder = Vec::new();
+
+    for _ in 0..numtoelect {
+        let mut numerator = vec![BigRational::one(); candidates.len()];
+        for nom in &nomlist {
+            for edge in &nom.edges {
+                numerator[edge.canindex()] += &budgets[nom.index] * &voterload[nom.index];
+            }
+        }
+        let mut best: Option<(usize, BigRational)> = None;
+        for canindex in 0..candidates.len() {
+            if elected[canindex] || !approval[canindex].is_positive() {
+                continue;
+            }
+            let score = &numerator[canindex] / &approval[canindex];
+            if best
+                .as_ref()
+                .is_none_or(|(_, bestscore)| score < *bestscore)
+            {
+                best = Some((canindex, score));
+            }
+        }
+        let Some((bestcandidate, score)) = best else {
+            break;
+        };
+        elected[bestcandidate] = true;
+        electionorder.push(bestcandidate);
+        for nom in &nomlist {
+            for edge in &nom.edges {
+                if edge.canindex() == bestcandidate {
+                    edgeload[edge.index()] = &score - &voterload[nom.index];
+                    voterload[nom.index] = score.clone();
+                }
+            }
+        }
+    }
+
+    let mut support = vec![BigRational::zero(); candidates.len()];
+    for nom in &nomlist {
+        if voterload[nom.index].is_positive() {
+            for edge in &nom.edges {
+                support[edge.canindex()] +=
+                    &budgets[nom.index] * &edgeload[edge.index()] / &voterload[nom.index];
+            }
+        }
+    }
+    electionorder
+        .into_iter()
+        .map(|index| (candidates[index].canid.clone(), support[index].clone()))
+        .collect()
+}
+
+/// `seq_phragmen` for at most `N` candidates and `M` voters, working only in
+/// fixed-size arrays (including an `M` by `N` table of edge loads, so keep
+/// the bounds small). The only allocation is the returned winner list, in
+/// election order with each winner's support, which matches
+/// `seq_phragmen(votelist.to_vec(), numtoelect).outcome().winners`. Inputs
+/// over either bound are rejected, as are repeated approvals within a
+/// ballot, since a voter has room for only `N` edges.
+pub fn seq_phragmen_bounded<const N: usize, const M: usize>(
+    votelist: &[(String, f64, Vec<String>)],
+    numtoelect: usize,
+) -> Result<Vec<(String, f64)>, PhragmenError> {
+    if votelist.len() > M {
+        return Err(PhragmenError::TooManyVoters {
+            limit: M,
+            voters: votelist.len(),
+        });
+    }
+    let mut canids: [&str; N] = [""; N];
+    let mut numcandidates = 0;
+    let mut edges = [[0usize; N]; M];
+    let mut numedges = [0usize; M];
+    for (voterindex, (voterid, _, approvals)) in votelist.iter().enumerate() {
+        for canid in approvals {
+            let canindex = match canids[..numcandidates].iter().position(|c| c == canid) {
+                Some(canindex) => canindex,
+                None if numcandidates < N => {
+                    canids[numcandidates] = canid;
+                    numcandidates += 1;
+                    numcandidates - 1
+                }
+                None => return Err(PhragmenError::TooManyCandidates { limit: N }),
+            };
+            if edges[voterindex][..numedges[voterindex]].contains(&canindex) {
+                return Err(PhragmenError::DuplicateApproval {
+                    index: voterindex,
+                    voterid: voterid.clone(),
+                    canid: canid.clone(),
+                });
+            }
+            edges[voterindex][numedges[voterindex]] = canindex;
+            numedges[voterindex] += 1;
+        }
+    }
+
+    let mut approval = [0.0; N];
+    for (voterindex, (_, budget, _)) in votelist.iter().enumerate() {
+        for &canindex in &edges[voterindex][..numedges[voterindex]] {
+            approval[canindex] += budget;
+        }
+    }
+    let mut voterload = [0.0; M];
+    let mut edgeload = [[0.0; N]; M];
+    let mut elected = [false; N];
+    let mut electionorder = [0usize; N];
+    let mut numelected = 0;
+    let mut score = [0.0; N];
+    while numelected < numtoelect.min(numcandidates) {
+        for canindex in 0..numcandidates {
+            if !elected[canindex] {
+                score[canindex] = if approval[canindex] > EPS {
+                    1.0 / approval[canindex]
+                } else {
+                    f64::INFINITY
+                };
+            }
+        }
+        for (voterindex, (_, budget, _)) in votelist.iter().enumerate() {
+            for &canindex in &edges[voterindex][..numedges[voterindex]] {
+                if !elected[canindex] && approval[canindex] > EPS {
+                    score[canindex] += budget * voterload[voterindex] / approval[canindex];
+                }
+            }
+        }
+        let mut bestcandidate = None;
+        let mut bestscore = f64::INFINITY;
+        for canindex in 0..numcandidates {
+            if !elected[canindex] && score[canindex] < bestscore {
+                bestscore = score[canindex];
+                bestcandidate = Some(canindex);
+            }
+        }
+        let Some(bestcandidate) = bestcandidate else {
+            break;
+        };
+        elected[bestcandidate] = true;
+        electionorder[numelected] = bestcandidate;
+        numelected += 1;
+        for voterindex in 0..votelist.len() {
+            for (edge, &canindex) in edges[voterindex][..numedges[voterindex]].iter().enumerate() {
+                if canindex == bestcandidate {
+                    let load = bestscore - voterload[voterindex];
+                    voterload[voterindex] += load - edgeload[voterindex][edge];
+                    edgeload[voterindex][edge] = load;
+                }
+            }
+        }
+    }
+
+    let mut support = [0.0; N];
+    for (voterindex, (_, budget, _)) in votelist.iter().enumerate() {
+        if voterload[voterindex] > EPS {
+            for (edge, &canindex) in edges[voterindex][..numedges[voterindex]].iter().enumerate() {
+                support[canindex] += budget * edgeload[voterindex][edge] / voterload[voterindex];
+            }
+        }
+    }
+    Ok(electionorder[..numelected]
+        .iter()
+        .map(|&canindex| (canids[canindex].to_string(), support[canindex]))
+        .collect())
+}
+
+/// Winner sets of the same election at each available precision, from
+/// `precision_audit`. Winner lists are sorted by id.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrecisionReport {
+    pub f32_winners: Vec<String>,
+    pub f64_winners: Vec<String>,
+    /// Exact rational winners; `None` without the `rational` feature.
+    pub rational_winners: Option<Vec<String>>,
+    /// Ids elected at some precisions but not all, sorted.
+    pub differing: Vec<String>,
+}
+
+impl PrecisionReport {
+    /// Whether every precision elected the same set.
+    pub fn agrees(&self) -> bool {
+        self.differing.is_empty()
+    }
+}
+
+/// Runs sequential Phragmén in `f32`, in `f64` (`seq_phragmen`) and, with
+/// the `rational` feature, in exact rationals, and compares the winner sets.
+/// Disagreement means near-ties that round-off decides, i.e. an
+/// ill-conditioned input whose outcome should not be trusted blindly.
+pub fn precision_audit<B: Into<Ballot>>(votelist: Vec<B>, numtoelect: usize) -> PrecisionReport {
+    let votelist = into_votelist(votelist);
+    let sorted = |mut winners: Vec<String>| {
+        winners.sort();
+        winners
+    };
+    let f32_winners = sorted(seq_phragmen_f32(&votelist, numtoelect));
+    #[cfg(feature = "rational")]
+    let rational_winners = Some(sorted(
+        seq_phragmen_rational(
+            votelist
+                .iter()
+                .map(|(voterid, budget, approvals)| {
+                    let budget = num_rational::BigRational::from_float(*budget)
+                        .unwrap_or_else(num_traits::Zero::zero);
+                    (voterid.clone(), budget, approvals.clone())
+                })
+                .collect(),
+            numtoelect,
+        )
+        .into_iter()
+        .map(|(canid, _)| canid)
+        .collect(),
+    ));
+    #[cfg(not(feature = "rational"))]
+    let rational_winners = None;
+    let f64_winners = sorted(
+        seq_phragmen(votelist, numtoelect)
+            .outcome()
+            .winners
+            .into_iter()
+            .map(|(canid, _)| canid)
+            .collect(),
+    );
+    let runs: Vec<&Vec<String>> = [Some(&f32_winners), Some(&f64_winners)]
+        .into_iter()
+        .chain([rational_winners.as_ref()])
+        .flatten()
+        .collect();
+    let all: HashSet<&String> = runs.iter().flat_map(|winners| winners.iter()).collect();
+    let mut differing: Vec<String> = all
+        .into_iter()
+        .filter(|canid| !runs.iter().all(|winners| winners.contains(canid)))
+        .cloned()
+        .collect();
+    differing.sort();
+    PrecisionReport {
+        f32_winners,
+        f64_winners,
+        rational_winners,
+        differing,
+    }
+}
+
+/// The winners of plain sequential Phragmén computed entirely in `f32`, in
+/// election order.
+fn seq_phragmen_f32(votelist: &[(String, f64, Vec<String>)], numtoelect: usize) -> Vec<String> {
+    let (nomlist, candidates) = setuplists(votelist.to_vec());
+    let mut approval = vec![0.0f32; candidates.len()];
+    for nom in &nomlist {
+        for edge in &nom.edges {
+            approval[edge.canindex()] += nom.budget as f32;
+        }
+    }
+    let mut voterload = vec![0.0f32; nomlist.len()];
+    let mut elected = vec![false; candidates.len()];
+    let mut winners = Vec::new();
+    let mut score = vec![0.0f32; candidates.len()];
+    for _ in 0..numtoelect {
+        for (canindex, score) in score.iter_mut().enumerate() {
+            *score = if approval[canindex] > 0.0 {
+                1.0 / approval[canindex]
+            } else {
+                f32::INFINITY
+            };
+        }
+        for nom in &nomlist {
+            for edge in &nom.edges {
+                if approval[edge.canindex()] > 0.0 {
+                    score[edge.canindex()] +=
+                        nom.budget as f32 * voterload[nom.index] / approval[edge.canindex()];
+                }
+            }
+        }
+        let mut bestcandidate = None;
+        let mut bestscore = f32::INFINITY;
+        for (canindex, &canscore) in score.iter().enumerate() {
+            if !elected[canindex] && canscore < bestscore {
+                bestscore = canscore;
+                bestcandidate = Some(canindex);
+            }
+        }
+        let Some(bestcandidate) = bestcandidate else {
+            break;
+        };
+        elected[bestcandidate] = true;
+        winners.push(candidates[bestcandidate].canid.clone());
+        for nom in &nomlist {
+            if nom
+                .edges
+                .iter()
+                .any(|edge| edge.canindex() == bestcandidate)
+            {
+                voterload[nom.index] = bestscore;
+            }
+        }
+    }
+    winners
+}
+
+pub fn run_election<B: Into<Ballot>>(votelist: Vec<B>, numtoelect: usize) -> Outcome {
+    seq_phragmen(votelist, numtoelect).outcome()
+}
+
+/// Lazily runs one election per input as the iterator is consumed, so an
+/// unbounded stream of ballot sets can be processed without collecting it.
+pub fn elections_stream<I, B>(inputs: I, numtoelect: usize) -> impl Iterator<Item = Outcome>
+where
+    I: Iterator<Item = Vec<B>>,
+    B: Into<Ballot>,
+{
+    inputs.map(move |votelist| run_election(votelist, numtoelect))
+}
+
+/// Runs independent elections and returns their outcomes in input order.
+/// With the `rayon` feature the elections are spread over the rayon thread
+/// pool; without it they run one after another.
+pub fn run_elections_parallel(elections: Vec<(VoteList, usize)>) -> Vec<Outcome> {
+    #[cfg(feature = "rayon")]
+    {
+        use rayon::prelude::*;
+        elections
+            .into_par_iter()
+            .map(|(votelist, numtoelect)| run_election(votelist, numtoelect))
+            .collect()
+    }
+    #[cfg(not(feature = "rayon"))]
+    {
+        elections
+            .into_iter()
+            .map(|(votelist, numtoelect)| run_election(votelist, numtoelect))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The ballots of the example in `main`.
+    fn sample() -> Vec<(String, f64, Vec<String>)> {
+        vec![
+            (
+                "A".to_string(),
+                10.0,
+                vec!["X".to_string(), "Y".to_string()],
+            ),
+            (
+                "B".to_string(),
+                20.0,
+                vec!["X".to_string(), "Z".to_string()],
+            ),
+            (
+                "C".to_string(),
+                30.0,
+                vec!["Y".to_string(), "Z".to_string()],
+            ),
+            ("C".to_string(), 50.0, vec!["Z".to_string()]),
+        ]
+    }
+
+    /// Ids, budgets and edge endpoints of built lists, one line per voter
+    /// and candidate, for comparing them.
+    fn lists_summary(voters: &[Voter], candidates: &[Candidate]) -> Vec<String> {
+        let voters = voters.iter().map(|voter| {
+            let edges: Vec<(usize, usize)> = voter
+                .edges
+                .iter()
+                .map(|edge| (edge.index(), edge.canindex()))
+                .collect();
+            format!("{} {} {edges:?}", voter.voterid, voter.budget)
+        });
+        let candidates = candidates
+            .iter()
+            .map(|candidate| format!("{} {}", candidate.index, candidate.canid));
+        voters.chain(candidates).collect()
+    }
+
+    #[test]
+    fn reserving_setup_matches_the_plain_one() {
+        let votelist = sample();
+        let (voters, candidates) = setuplists(votelist.clone());
+        // Hints above the sizes reached, so the reservation is what shows.
+        let (reserved_voters, reserved_candidates) =
+            setuplists_with_capacity(votelist.clone(), 16, 32);
+        assert_eq!(
+            lists_summary(&reserved_voters, &reserved_candidates),
+            lists_summary(&voters, &candidates)
+        );
+        assert!(reserved_voters.capacity() >= 32);
+        assert!(reserved_candidates.capacity() >= 16);
+        for (voter, ballot) in reserved_voters.iter().zip(&votelist) {
+            assert!(voter.edges.capacity() >= ballot.2.len());
+        }
+
+        // Wrong hints cost reallocations, never a different result.
+        let (small_voters, small_candidates) = setuplists_with_capacity(votelist, 1, 1);
+        assert_eq!(
+            lists_summary(&small_voters, &small_candidates),
+            lists_summary(&voters, &candidates)
+        );
+    }
+
+    #[test]
+    fn negative_bias_promotes_the_runner_up() {
+        let votelist = vec![
+            ("A".to_string(), 10.0, vec!["X".to_string()]),
+            ("B".to_string(), 9.9, vec!["Y".to_string()]),
+        ];
+        let plain = seq_phragmen(votelist.clone(), 1);
+        assert_eq!(plain.outcome().winners[0].0, "X");
+
+        let options = PhragmenOptions {
+            score_bias: HashMap::from([("Y".to_string(), -0.01)]),
+            ..Default::default()
+        };
+        let biased = seq_phragmen_with(votelist, 1, &options);
+        let winners = biased.outcome().winners;
+        assert_eq!(winners.len(), 1);
+        assert_eq!(winners[0].0, "Y");
+        // The bias picks the winner but the load is the unbiased score.
+        assert!((winners[0].1 - 9.9).abs() < 1e-9);
+        assert!(biased.voter_loads().iter().all(|&load| load >= 0.0));
+    }
+
+    #[test]
+    fn parallel_elections_match_serial_runs() {
+        let district = vec![
+            ("D".to_string(), 5.0, vec!["P".to_string()]),
+            ("E".to_string(), 3.0, vec!["P".to_string(), "Q".to_string()]),
+        ];
+        let elections: Vec<(VoteList, usize)> = vec![
+            (sample(), 1),
+            (district.clone(), 2),
+            (sample(), 3),
+            (district, 1),
+            (sample(), 2),
+        ];
+        let serial: Vec<Outcome> = elections
+            .iter()
+            .map(|(votelist, numtoelect)| run_election(votelist.clone(), *numtoelect))
+            .collect();
+        let parallel = run_elections_parallel(elections);
+        assert_eq!(parallel, serial);
+        // Input order is kept.
+        assert_eq!(parallel[1].winners.len(), 2);
+        assert_eq!(parallel[3].winners[0].0, "P");
+    }
+
+    #[test]
+    fn deterministic_mode_ignores_the_input_order() {
+        let options = PhragmenOptions {
+            deterministic: true,
+            ..Default::default()
+        };
+        let mut votelist = sample();
+        votelist.push((
+            "D".to_string(),
+            15.0,
+            vec!["Z".to_string(), "Y".to_string(), "X".to_string()],
+        ));
+        votelist.push(("E".to_string(), 5.0, vec!["W".to_string(), "X".to_string()]));
+        let mut reversed = votelist.clone();
+        reversed.reverse();
+        for ballot in &mut reversed {
+            ballot.2.reverse();
+        }
+        let mut rotated = votelist.clone();
+        rotated.rotate_left(2);
+        for ballot in &mut rotated {
+            let len = ballot.2.len();
+            ballot.2.rotate_left(len / 2);
+        }
+        let supports: Vec<Vec<u64>> = [votelist, reversed, rotated]
+            .into_iter()
+            .map(|votelist| {
+                let a = seq_phragmen_with(votelist, 3, &options);
+                a.cansupport
+                    .iter()
+                    .map(|support| support.to_bits())
+                    .collect()
+            })
+            .collect();
+        assert_eq!(supports[0], supports[1]);
+        assert_eq!(supports[0], supports[2]);
+    }
+
+    #[test]
+    fn heavier_seats_load_their_backers_more() {
+        let first_round = |seat_weight: f64| {
+            let options = PhragmenOptions {
+                seat_weight,
+                ..Default::default()
+            };
+            let a = seq_phragmen_with(sample(), 1, &options);
+            let winner = *a.electedcandidates.iter().next().unwrap();
+            (winner, a)
+        };
+        let (winner, plain) = first_round(1.0);
+        let (heavy_winner, heavy) = first_round(2.0);
+        assert_eq!(heavy_winner, winner);
+        let winnerid = &plain.candidates[winner].canid;
+        for voter in &plain.voterlist {
+            let backs = sample()[voter.index].2.contains(winnerid);
+            let load = plain.voter_loads()[voter.index];
+            let heavy_load = heavy.voter_loads()[voter.index];
+            if backs {
+                assert!(load > 0.0);
+                assert!((heavy_load - 2.0 * load).abs() < 1e-12);
+            } else {
+                assert_eq!(heavy_load, 0.0);
+            }
+        }
+    }
+
+    #[test]
+    fn vote_sets_convert_from_maps_and_borrowed_tuples() {
+        let map = HashMap::from([
+            (
+                "B".to_string(),
+                (20.0, vec!["X".to_string(), "Z".to_string()]),
+            ),
+            (
+                "A".to_string(),
+                (10.0, vec!["X".to_string(), "Y".to_string()]),
+            ),
+            (
+                "C".to_string(),
+                (30.0, vec!["Y".to_string(), "Z".to_string()]),
+            ),
+        ]);
+        let from_map = VoteSet::try_from(map).unwrap().into_inner();
+        assert_eq!(from_map, sample()[..3].to_vec());
+
+        let borrowed = vec![
+            ("A", 10.0, vec!["X", "Y"]),
+            ("B", 20.0, vec!["X", "Z"]),
+            ("C", 30.0, vec!["Y", "Z"]),
+            ("C", 50.0, vec!["Z"]),
+        ];
+        let from_tuples = VoteSet::try_from(borrowed).unwrap().into_inner();
+        assert_eq!(from_tuples, sample());
+        assert_eq!(
+            seq_phragmen(from_tuples, 2).outcome(),
+            seq_phragmen(sample(), 2).outcome()
+        );
+        assert_eq!(seq_phragmen(from_map, 2).outcome().winners.len(), 2);
+
+        let negative = vec![("A", -1.0, vec!["X"])];
+        assert!(VoteSet::try_from(negative).is_err());
+    }
+
+    fn winner_ids(a: &Assignment) -> Vec<String> {
+        a.outcome()
+            .winners
+            .into_iter()
+            .map(|(canid, _)| canid)
+            .collect()
+    }
+
+    #[test]
+    fn divisor_methods_agree_with_phragmen_on_the_sample() {
+        // Z wins on approval alone. For the second seat Y's backers without
+        // a seat outweigh X's: Phragmén scores Y 1.3/40 against X 1.2/30,
+        // D'Hondt counts 25 against 20 and Sainte-Laguë 20 against 16.7.
+        let expected = ["Z", "Y"];
+        assert_eq!(winner_ids(&seq_phragmen(sample(), 2)), expected);
+        for divisor in [DivisorKind::DHondt, DivisorKind::SainteLague] {
+            let a = divisor_method(sample(), 2, divisor);
+            assert_eq!(winner_ids(&a), expected, "{divisor:?}");
+            assert!(a.voter_loads().iter().all(|&load| load == 0.0));
+        }
+    }
+
+    #[test]
+    fn only_the_large_backer_is_pivotal() {
+        // X leads 11 to 9.5; without A it falls to 1, without D it still
+        // has 10.
+        let votelist = vec![
+            ("A".to_string(), 10.0, vec!["X".to_string()]),
+            ("D".to_string(), 1.0, vec!["X".to_string()]),
+            ("B".to_string(), 9.5, vec!["Y".to_string()]),
+        ];
+        let a = seq_phragmen(votelist, 1);
+        assert_eq!(winner_ids(&a), ["X"]);
+        assert_eq!(a.pivotal_voters("X"), ["A"]);
+        assert!(a.pivotal_voters("Y").is_empty());
+    }
+
+    #[test]
+    fn weakest_winner_defines_the_minimum_support() {
+        // X, elected last, ends with the least support of the three.
+        let a = seq_phragmen(sample(), 3);
+        let (candidate, support) = a.weakest_winner().unwrap();
+        assert_eq!(candidate.canid, "X");
+        assert_eq!(support, a.election_score().0);
+        assert!(a
+            .electedcandidates
+            .iter()
+            .all(|&index| a.cansupport[index] >= support));
+        assert!(seq_phragmen(sample(), 0).weakest_winner().is_none());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn restored_state_finishes_like_an_uninterrupted_run() {
+        let options = PhragmenOptions::default();
+        let votelist = sample();
+        let (voters, candidates) = setuplists(votelist.clone());
+        let mut a = Assignment::new(voters, candidates, None);
+        a.elect_next(&options).unwrap();
+        let state = a.save_state().unwrap();
+        drop(a);
+
+        let mut restored = Assignment::load_state(&state).unwrap();
+        for _ in 0..2 {
+            restored.elect_next(&options).unwrap();
+        }
+        restored.loadstoweights();
+        assert_eq!(restored.outcome(), seq_phragmen(votelist, 3).outcome());
+    }
+
+    #[test]
+    fn loads_below_eps_count_as_zero() {
+        let (voters, candidates) = setuplists(sample());
+        let mut a = Assignment::new(voters, candidates, None);
+        a.voterload.fill(1e-15);
+        a.edgeload.fill(1e-15);
+        a.loadstoweights();
+        assert!(a.edgeweight.iter().all(|&weight| weight == 0.0));
+        assert!(a.cansupport.iter().all(|&support| support == 0.0));
+
+        a.edgeweight.fill(1e-15);
+        a.cansupport.fill(1e-15);
+        a.weightstoloads();
+        assert!(a.edgeload.iter().all(|&load| load == 1e-15));
+
+        // A smaller threshold takes the same loads at face value.
+        a.eps = 1e-18;
+        a.cansupport.fill(0.0);
+        a.loadstoweights();
+        assert!(a.edgeweight.iter().all(|weight| weight.is_finite()));
+        assert!(a.cansupport.iter().any(|&support| support > 1.0));
+
+        let options = PhragmenOptions {
+            eps: 1e-9,
+            ..Default::default()
+        };
+        assert_eq!(seq_phragmen_with(sample(), 2, &options).eps, 1e-9);
+    }
+
+    #[test]
+    fn disjoint_clusters_form_two_components() {
+        let votelist = vec![
+            ("A".to_string(), 1.0, vec!["X".to_string(), "Y".to_string()]),
+            ("B".to_string(), 1.0, vec!["P".to_string()]),
+            ("C".to_string(), 1.0, vec!["Y".to_string(), "Z".to_string()]),
+            ("D".to_string(), 1.0, vec!["P".to_string(), "Q".to_string()]),
+        ];
+        let components = connected_components(&votelist);
+        let set = |ids: &[&str]| ids.iter().map(|id| id.to_string()).collect::<HashSet<_>>();
+        assert_eq!(components, [set(&["X", "Y", "Z"]), set(&["P", "Q"])]);
+    }
+
+    #[test]
+    fn winners_rank_by_support_then_id() {
+        let ranked = seq_phragmen(sample(), 3).winners_by_support();
+        let ids: Vec<&str> = ranked.iter().map(|(canid, _)| canid.as_str()).collect();
+        assert_eq!(ids, ["Z", "Y", "X"]);
+        assert!(ranked.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+
+        let tied = vec![
+            ("A".to_string(), 1.0, vec!["X".to_string()]),
+            ("B".to_string(), 1.0, vec!["W".to_string()]),
+        ];
+        let a = seq_phragmen(tied, 2);
+        assert_eq!(winner_ids(&a), ["X", "W"]);
+        let ranked = a.winners_by_support();
+        assert_eq!(ranked, [("W".to_string(), 1.0), ("X".to_string(), 1.0)]);
+    }
+
+    #[test]
+    fn support_floor_skips_the_weak_favourite() {
+        let votelist = vec![
+            ("A".to_string(), 10.0, vec!["X".to_string()]),
+            ("B".to_string(), 9.9, vec!["Y".to_string()]),
+        ];
+        // The bias alone would elect Y with support 9.9.
+        let mut options = PhragmenOptions {
+            score_bias: HashMap::from([("Y".to_string(), -0.01)]),
+            ..Default::default()
+        };
+        assert_eq!(
+            winner_ids(&seq_phragmen_with(votelist.clone(), 1, &options)),
+            ["Y"]
+        );
+
+        options.support_floor = Some(9.95);
+        assert_eq!(
+            winner_ids(&seq_phragmen_with(votelist.clone(), 1, &options)),
+            ["X"]
+        );
+        // Y never clears the floor, so the second seat stays empty.
+        assert_eq!(
+            winner_ids(&seq_phragmen_with(votelist.clone(), 2, &options)),
+            ["X"]
+        );
+
+        options.support_floor = Some(20.0);
+        assert!(winner_ids(&seq_phragmen_with(votelist, 1, &options)).is_empty());
+    }
+
+    #[test]
+    fn rust_literal_names_every_voter_and_balances() {
+        let mut votelist = sample();
+        votelist.push(("quote\"d".to_string(), f64::INFINITY, Vec::new()));
+        let literal = votelist_to_rust_literal(&votelist);
+        assert!(literal.starts_with("vec!["));
+        for (voterid, _, _) in &votelist {
+            assert!(literal.contains(&format!("{voterid:?}.to_string()")));
+        }
+        assert!(literal.contains("f64::INFINITY"));
+
+        let mut open = Vec::new();
+        let mut chars = literal.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => {
+                    // Skip string contents, escapes included.
+                    while let Some(c) = chars.next() {
+                        match c {
+                            '\\' => {
+                                chars.next();
+                            }
+                            '"' => break,
+                            _ => {}
+                        }
+                    }
+                }
+                '(' | '[' => open.push(c),
+                ')' => assert_eq!(open.pop(), Some('(')),
+                ']' => assert_eq!(open.pop(), Some('[')),
+                _ => {}
+            }
+        }
+        assert!(open.is_empty());
+    }
+
+    #[test]
+    fn similarity_is_one_for_itself_and_zero_when_disjoint() {
+        let outcome = seq_phragmen(sample(), 2).outcome();
+        assert_eq!(assignment_similarity(&outcome, &outcome), 1.0);
+
+        let disjoint = vec![
+            ("P".to_string(), 10.0, vec!["Q".to_string()]),
+            ("R".to_string(), 5.0, vec!["S".to_string()]),
+        ];
+        let other = seq_phragmen(disjoint, 2).outcome();
+        assert_eq!(assignment_similarity(&outcome, &other), 0.0);
+        assert_eq!(assignment_similarity(&other, &outcome), 0.0);
+
+        let three = seq_phragmen(sample(), 3).outcome();
+        let partial = assignment_similarity(&outcome, &three);
+        assert!(partial > 0.0 && partial < 1.0);
+    }
+
+    #[test]
+    fn batches_with_a_passive_callback_match_one_run() {
+        let mut calls = 0;
+        let batched = seq_phragmen_batched(sample(), vec![1, 2], |outcome, _| {
+            assert_eq!(outcome.winners.len(), 1);
+            assert_eq!(outcome.winners[0].0, "Z");
+            calls += 1;
+        });
+        assert_eq!(calls, 1);
+        assert_eq!(batched.outcome(), seq_phragmen(sample(), 3).outcome());
+    }
+
+    #[test]
+    fn compact_edges_keep_ids_and_shrink_memory() {
+        use std::mem::size_of;
+
+        // Three u32 indices, against the two ids and an index a
+        // string-keyed edge would need.
+        assert_eq!(size_of::<Edge>(), 3 * size_of::<u32>());
+        let string_edge = 2 * size_of::<String>() + size_of::<usize>();
+        assert!(100_000 * size_of::<Edge>() * 4 <= 100_000 * string_edge);
+
+        // 2,000 voters approving 50 of 200 candidates each: 100k edges.
+        let votelist: VoteList = (0..2_000)
+            .map(|voter| {
+                let approvals = (0..50)
+                    .map(|k| format!("c{}", (voter + 3 * k) % 200))
+                    .collect();
+                (format!("v{voter}"), 1.0 + (voter % 7) as f64, approvals)
+            })
+            .collect();
+        let (voters, candidates) = setuplists(votelist.clone());
+        let a = Assignment::new(voters, candidates, None);
+        assert_eq!(a.edgelist.len(), 100_000);
+        assert_eq!(a.candidates.len(), 200);
+        for (ballot, rebuilt) in votelist.iter().zip(a.votelist()) {
+            let mut approvals = ballot.2.clone();
+            let mut rebuilt_approvals = rebuilt.2.clone();
+            approvals.sort();
+            rebuilt_approvals.sort();
+            assert_eq!((&ballot.0, ballot.1), (&rebuilt.0, rebuilt.1));
+            assert_eq!(approvals, rebuilt_approvals);
+        }
+
+        // Rebuilding the ballots from the indices gives the same winners.
+        let a = seq_phragmen(sample(), 3);
+        assert_eq!(a.votelist(), sample());
+        assert_eq!(winner_ids(&seq_phragmen(a.votelist(), 3)), winner_ids(&a));
+    }
+
+    #[test]
+    fn elections_beyond_u32_indices_are_errors() {
+        assert_eq!(check_index_limits(MAX_INDEX, MAX_INDEX), Ok(()));
+        assert_eq!(
+            check_index_limits(MAX_INDEX + 1, 0),
+            Err(PhragmenError::TooManyVoters {
+                limit: MAX_INDEX,
+                voters: MAX_INDEX + 1
+            })
+        );
+        assert_eq!(
+            check_index_limits(1, MAX_INDEX + 1),
+            Err(PhragmenError::TooManyEdges {
+                limit: MAX_INDEX,
+                edges: MAX_INDEX + 1
+            })
+        );
+        assert!(try_seq_phragmen_with(sample(), 2, &PhragmenOptions::default()).is_ok());
+        assert!(try_setuplists(sample()).is_ok());
+    }
+
+    #[test]
+    fn sensitivity_spans_no_influence_to_the_baseline() {
+        let votelist = sample();
+        // Both ballots cast as C scale together; without them X takes a
+        // seat.
+        let rows = sensitivity(votelist.clone(), 2, "C", &[0.0, 1.0]);
+        assert_eq!(rows.len(), 2);
+
+        let mut without_c = votelist.clone();
+        without_c.retain(|ballot| ballot.0 != "C");
+        let mut expected = winner_ids(&seq_phragmen(without_c, 2));
+        expected.sort();
+        assert!(expected.contains(&"X".to_string()));
+        assert_eq!(rows[0], (0.0, expected));
+
+        let mut baseline = winner_ids(&seq_phragmen(votelist, 2));
+        baseline.sort();
+        assert_eq!(rows[1], (1.0, baseline));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn ndjson_writes_one_line_per_seat() {
+        let mut buffer: Vec<u8> = Vec::new();
+        let a = seq_phragmen_ndjson(sample(), 3, &mut buffer).unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+        let lines: Vec<serde_json::Value> = text
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 3);
+        for (round, (line, canid)) in lines.iter().zip(winner_ids(&a)).enumerate() {
+            assert_eq!(line["round"], round + 1);
+            assert_eq!(line["elected"], canid.as_str());
+            assert_eq!(line["supports"].as_object().unwrap().len(), round + 1);
+        }
+    }
+
+    #[test]
+    fn out_of_range_lookups_return_none() {
+        let a = seq_phragmen(sample(), 2);
+        assert_eq!(a.candidate(2).unwrap().canid, "Z");
+        assert_eq!(a.voter(3).unwrap().budget, 50.0);
+        assert!(a.candidate(3).is_none());
+        assert!(a.voter(4).is_none());
+        assert!(a.candidate(usize::MAX).is_none());
+        assert!(a.voter(usize::MAX).is_none());
+        assert_eq!(a.candidate_by_id("Y").unwrap().index, 1);
+        assert!(a.candidate_by_id("W").is_none());
+        assert_eq!(a.voter_by_id("B").unwrap().index, 1);
+        assert!(a.voter_by_id("D").is_none());
+    }
+
+    #[test]
+    fn default_load_strategy_reproduces_the_plain_update() {
+        let winners = seq_phragmen(sample(), 2).outcome().winners;
+        assert_eq!(
+            winners,
+            [
+                ("Z".to_string(), 79.23076923076923),
+                ("Y".to_string(), 30.769230769230766)
+            ]
+        );
+
+        let options = PhragmenOptions {
+            load_strategy: LoadStrategy::Custom(|score, voterload, _| score - voterload),
+            ..Default::default()
+        };
+        for numtoelect in 1..=3 {
+            assert_eq!(
+                seq_phragmen_with(sample(), numtoelect, &options).outcome(),
+                seq_phragmen(sample(), numtoelect).outcome()
+            );
+        }
+    }
+
+    #[test]
+    fn wasted_budget_is_the_losing_only_voters() {
+        let mut votelist = sample();
+        votelist.push(("D".to_string(), 4.0, vec!["W".to_string()]));
+        votelist.push(("E".to_string(), 3.5, vec!["W".to_string(), "V".to_string()]));
+        votelist.push(("F".to_string(), 2.0, Vec::new()));
+        let a = seq_phragmen(votelist, 2);
+        assert_eq!(winner_ids(&a), ["Z", "Y"]);
+        assert!((a.total_wasted_budget() - 9.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn precomputed_approval_matches_the_recomputing_path() {
+        let options = PhragmenOptions::default();
+        let (voters, candidates) = setuplists(sample());
+        let mut recomputed = Assignment::new(voters.clone(), candidates.clone(), None);
+        let approval = recomputed.canapproval.clone();
+        let mut trusted = Assignment::with_approval(voters, candidates, approval);
+        for _ in 0..3 {
+            assert_eq!(
+                trusted.elect_next(&options),
+                recomputed.elect_next(&options)
+            );
+        }
+        trusted.loadstoweights();
+        recomputed.loadstoweights();
+        assert_eq!(trusted.outcome(), recomputed.outcome());
+    }
+
+    #[test]
+    fn min_approval_drops_a_candidate_and_reindexes() {
+        // X has 30 approval, Y 40 and Z 100.
+        let options = PhragmenOptions {
+            min_approval: Some(35.0),
+            ..Default::default()
+        };
+        let a = seq_phragmen_with(sample(), 3, &options);
+        assert!(a.candidate_by_id("X").is_none());
+        let canids: Vec<&str> = a.candidates.iter().map(|c| c.canid.as_str()).collect();
+        assert_eq!(canids, ["Y", "Z"]);
+        for (index, candidate) in a.candidates.iter().enumerate() {
+            assert_eq!(candidate.index, index);
+        }
+        for (index, edge) in a.edgelist.iter().enumerate() {
+            assert_eq!(edge.index(), index);
+            assert!(edge.canindex() < 2);
+        }
+        assert_eq!(a.edgelist.len(), 5);
+        assert_eq!(a.voterlist.len(), 4);
+        assert_eq!(winner_ids(&a), ["Z", "Y"]);
+    }
+
+    #[test]
+    fn group_shares_each_sum_to_one() {
+        let outcome = seq_phragmen(sample(), 2).outcome();
+        let group_of: HashMap<String, String> = [("A", "north"), ("B", "north"), ("C", "south")]
+            .into_iter()
+            .map(|(voterid, group)| (voterid.to_string(), group.to_string()))
+            .collect();
+        let shares = group_representation(&outcome, &group_of);
+        assert_eq!(shares.len(), 2);
+        let (budget_total, support_total) = shares
+            .values()
+            .fold((0.0, 0.0), |(b, s), (budget, support)| {
+                (b + budget, s + support)
+            });
+        assert!((budget_total - 1.0).abs() < 1e-12);
+        assert!((support_total - 1.0).abs() < 1e-12);
+        assert!((shares["north"].0 - 30.0 / 110.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn weights_computed_later_match_the_always_on_path() {
+        let variants = [
+            PhragmenOptions::default(),
+            PhragmenOptions {
+                weight_distribution: WeightDistribution::WinnersOnly,
+                ..Default::default()
+            },
+            PhragmenOptions {
+                max_support: Some(60.0),
+                eps: 1e-9,
+                ..Default::default()
+            },
+        ];
+        for options in variants {
+            let always = seq_phragmen_with(sample(), 3, &options);
+            let lazy_options = PhragmenOptions {
+                compute_weights: false,
+                ..options.clone()
+            };
+            let mut lazy = seq_phragmen_with(sample(), 3, &lazy_options);
+            assert!(lazy.cansupport.iter().all(|&support| support == 0.0));
+            assert_eq!(winner_ids(&lazy), winner_ids(&always));
+            lazy.ensure_weights(&options);
+            assert_eq!(lazy.outcome(), always.outcome());
+        }
+    }
+
+    #[test]
+    fn winners_only_moves_a_losers_share_to_the_winners() {
+        let options = PhragmenOptions {
+            compute_weights: false,
+            ..Default::default()
+        };
+        let mut a = seq_phragmen_with(sample(), 2, &options);
+        assert_eq!(winner_ids(&a), ["Z", "Y"]);
+        let x = a.candidate_by_id("X").unwrap().index;
+        let y = a.candidate_by_id("Y").unwrap().index;
+        // A backs the winner Y and the loser X; leave load on the X edge.
+        let voter_a = a.voter_by_id("A").unwrap().clone();
+        let onx = voter_a.edges.iter().find(|e| e.canindex() == x).unwrap();
+        let ony = voter_a.edges.iter().find(|e| e.canindex() == y).unwrap();
+        let yload = a.edgeload[ony.index()];
+        a.setload(onx, yload);
+
+        let mut all_edges = a.clone();
+        all_edges.loadstoweights();
+        let mut winners_only = a;
+        winners_only.loadstoweights_winners_only(PhragmenOptions::default().eps);
+
+        assert!((all_edges.edgeweight[onx.index()] - 5.0).abs() < 1e-9);
+        assert!((all_edges.edgeweight[ony.index()] - 5.0).abs() < 1e-9);
+        assert_eq!(winners_only.edgeweight[onx.index()], 0.0);
+        assert!((winners_only.edgeweight[ony.index()] - 10.0).abs() < 1e-9);
+        assert_eq!(winners_only.cansupport[x], 0.0);
+        assert!((winners_only.cansupport[y] - all_edges.cansupport[y] - 5.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn winners_score_below_every_unelected_candidate() {
+        // X, left out, now costs A (0.0325) and B (0.01):
+        // (1 + 10 * 0.0325 + 20 * 0.01) / 30.
+        let a = seq_phragmen(sample(), 2);
+        let scores = a.final_scores();
+        let canids: Vec<&str> = scores.iter().map(|(canid, _)| canid.as_str()).collect();
+        assert_eq!(canids, ["Z", "Y", "X"]);
+        assert_eq!(scores[0].1, 0.01);
+        assert_eq!(scores[1].1, 1.3 / 40.0);
+        assert!((scores[2].1 - 1.525 / 30.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn edgeless_voters_elect_nobody_without_panicking() {
+        let votelist: VoteList = vec![
+            ("A".to_string(), 10.0, Vec::new()),
+            ("B".to_string(), 0.0, Vec::new()),
+        ];
+        let a = seq_phragmen(votelist, 3);
+        assert!(a.candidates.is_empty());
+        assert!(a.electedcandidates.is_empty());
+        assert!(a.outcome().winners.is_empty());
+        assert!(seq_phragmen(VoteList::new(), 1)
+            .outcome()
+            .winners
+            .is_empty());
+    }
+
+    #[test]
+    fn full_ranking_covers_every_candidate_once() {
+        let mut votelist = sample();
+        // A candidate nobody funds still gets the last round.
+        votelist.push(("zero".to_string(), 0.0, vec!["nobody".to_string()]));
+        let ranking = seq_phragmen_full(votelist.clone());
+        let (_, candidates) = setuplists(votelist);
+        assert_eq!(ranking.len(), candidates.len());
+        let canids: HashSet<&str> = ranking.iter().map(|(canid, _)| canid.as_str()).collect();
+        assert_eq!(canids.len(), candidates.len());
+        let mut rounds: Vec<usize> = ranking.iter().map(|&(_, round)| round).collect();
+        rounds.sort();
+        assert_eq!(rounds, (1..=candidates.len()).collect::<Vec<_>>());
+        assert_eq!(
+            ranking.last().unwrap(),
+            &("nobody".to_string(), candidates.len())
+        );
+    }
+
+    #[test]
+    fn warm_start_from_a_converged_run_needs_at_most_one_sweep() {
+        let mut converged = seq_phragmen(sample(), 3);
+        assert!(matches!(
+            converged.reach_equilibrium(1000, 1e-9),
+            Equilibrium::Converged { .. }
+        ));
+
+        let mut warm = seq_phragmen(sample(), 3);
+        warm.warm_start_from(&converged);
+        let status = warm.reach_equilibrium(1000, 1e-9);
+        assert!(matches!(status, Equilibrium::Converged { .. }));
+        assert!(status.iterations() <= 1);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn candidate_metadata_reaches_losers_and_the_report() {
+        let meta = HashMap::from([
+            ("X".to_string(), serde_json::json!({"party": "blue"})),
+            ("Z".to_string(), serde_json::json!({"party": "green"})),
+        ]);
+        let plain = seq_phragmen(sample(), 2);
+        let a = plain.clone().with_candidate_meta(meta);
+        assert_eq!(a.outcome(), plain.outcome());
+        let x = a.candidate_by_id("X").unwrap().index;
+        assert!(!a.electedcandidates.contains(&x));
+        assert_eq!(a.candidate_meta("X").unwrap()["party"], "blue");
+        assert!(a.candidate_meta("Y").is_none());
+
+        let report = a.json_report();
+        let winners = report["winners"].as_array().unwrap();
+        assert_eq!(winners[0]["id"], "Z");
+        assert_eq!(winners[0]["meta"]["party"], "green");
+        assert!(winners[1]["meta"].is_null());
+        assert_eq!(report["candidate_meta"]["X"]["party"], "blue");
+    }
+
+    #[test]
+    fn setup_indices_stay_consistent() {
+        // An empty ballot, a repeated voter id and a candidate approved
+        // from several ballots, the cases the bookkeeping can get wrong.
+        let mut votelist = sample();
+        votelist.insert(1, (String::new(), 0.0, Vec::new()));
+        votelist.push(("A".to_string(), 3.0, vec!["W".to_string(), "X".to_string()]));
+        let numedges: usize = votelist.iter().map(|v| v.2.len()).sum();
+        let (voters, candidates) = setuplists(votelist.clone());
+        let a = Assignment::new(voters, candidates, None);
+        assert_eq!(a.edges().len(), numedges);
+        assert_eq!(a.voterlist.len(), votelist.len());
+        assert_eq!(a.candidates.len(), 4);
+        let mut nextedge = 0;
+        for voter in &a.voterlist {
+            for edge in &voter.edges {
+                assert_eq!(edge.index(), nextedge);
+                assert_eq!(edge.voterindex(), voter.index);
+                assert!(edge.canindex() < a.candidates.len());
+                nextedge += 1;
+            }
+        }
+        let mut approval = vec![0.0; a.candidates.len()];
+        for (_, budget, approvals) in &votelist {
+            for canid in approvals {
+                approval[a.candidate_by_id(canid).unwrap().index] += budget;
+            }
+        }
+        assert_eq!(approval.as_slice(), a.approvals());
+    }
+
+    #[test]
+    fn budget_percentiles_on_a_known_distribution() {
+        let ballots = |budgets: &[f64]| -> VoteList {
+            budgets
+                .iter()
+                .map(|&budget| (format!("v{budget}"), budget, Vec::new()))
+                .collect()
+        };
+        // 1 to 11 out of order: ranks are exact.
+        let budgets: Vec<f64> = [7, 3, 11, 1, 9, 5, 2, 10, 4, 8, 6]
+            .into_iter()
+            .map(f64::from)
+            .collect();
+        let stats = budget_stats(&ballots(&budgets));
+        assert_eq!((stats.min, stats.max), (1.0, 11.0));
+        assert_eq!(stats.median, 6.0);
+        assert_eq!(stats.p90, 10.0);
+
+        // 1 to 10: both percentiles interpolate.
+        let budgets: Vec<f64> = (1..=10).map(f64::from).collect();
+        let stats = budget_stats(&ballots(&budgets));
+        assert_eq!(stats.median, 5.5);
+        assert!((stats.p90 - 9.1).abs() < 1e-12);
+
+        assert_eq!(budget_stats(&ballots(&[4.0; 5])).gini, 0.0);
+        let concentrated = budget_stats(&ballots(&[0.0, 0.0, 0.0, 12.0]));
+        assert!((concentrated.gini - 0.75).abs() < 1e-12);
+        assert_eq!(budget_stats(&[]), BudgetStats::default());
+    }
+
+    #[test]
+    fn reserved_group_gets_its_minimum() {
+        let options = PhragmenOptions {
+            candidate_group: HashMap::from([("X".to_string(), "G".to_string())]),
+            min_per_group: HashMap::from([("G".to_string(), 1)]),
+            ..Default::default()
+        };
+        // X scores worst, but the last seat is reserved for group G.
+        assert_eq!(winner_ids(&seq_phragmen(sample(), 2)), ["Z", "Y"]);
+        let a = try_seq_phragmen_with(sample(), 2, &options).unwrap();
+        assert_eq!(winner_ids(&a), ["Z", "X"]);
+
+        let too_many = PhragmenOptions {
+            min_per_group: HashMap::from([("G".to_string(), 3)]),
+            ..options.clone()
+        };
+        assert_eq!(
+            try_seq_phragmen_with(sample(), 2, &too_many).unwrap_err(),
+            PhragmenError::GroupMinimumsExceedSeats {
+                required: 3,
+                seats: 2
+            }
+        );
+        let unreachable = PhragmenOptions {
+            min_per_group: HashMap::from([("G".to_string(), 2)]),
+            ..options
+        };
+        assert!(matches!(
+            try_seq_phragmen_with(sample(), 2, &unreachable),
+            Err(PhragmenError::GroupMinimumUnreachable { available: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn marginal_seat_is_the_last_winner_of_the_larger_run() {
+        let larger = seq_phragmen(sample(), 3);
+        let (canid, minsupport) = marginal_seat(sample(), 2).unwrap();
+        assert_eq!(canid, "X");
+        assert_eq!(&canid, winner_ids(&larger).last().unwrap());
+        assert_eq!(minsupport, larger.election_score().0);
+        assert!(marginal_seat(sample(), 3).is_none());
+    }
+
+    #[test]
+    fn verify_solution_accepts_a_valid_outcome() {
+        let votelist = sample();
+        let a = seq_phragmen(votelist.clone(), 2);
+        let outcome = a.outcome();
+        let winners = winner_ids(&a);
+        let minsupport = verify_solution(&votelist, &winners, &outcome.assignments).unwrap();
+        assert!((minsupport - outcome.winners[1].1).abs() < 1e-9);
+    }
+
+    #[test]
+    fn verify_solution_rejects_overspending_and_non_winners() {
+        let votelist = sample();
+        let winners = vec!["Z".to_string(), "Y".to_string()];
+        let overspent = vec![("A".to_string(), vec![("Y".to_string(), 11.0)])];
+        assert_eq!(
+            verify_solution(&votelist, &winners, &overspent),
+            Err(VerificationError::BudgetExceeded {
+                voterid: "A".to_string(),
+                budget: 10.0,
+                assigned: 11.0
+            })
+        );
+        let to_loser = vec![("A".to_string(), vec![("X".to_string(), 5.0)])];
+        assert_eq!(
+            verify_solution(&votelist, &winners, &to_loser),
+            Err(VerificationError::NotAWinner {
+                voterid: "A".to_string(),
+                canid: "X".to_string()
+            })
+        );
+        // The two ballots cast as C pool their budgets.
+        let pooled = vec![("C".to_string(), vec![("Z".to_string(), 80.0)])];
+        assert_eq!(verify_solution(&votelist, &winners, &pooled), Ok(0.0));
+    }
+
+    #[test]
+    fn seat_costs_are_non_negative_and_rise_with_the_rounds() {
+        // Z costs its three backers 1/100 each; Y then raises A to 1.3/40
+        // and C from 1/100 to 1.3/40; X raises A and B to 1.525/30.
+        let costs = seq_phragmen(sample(), 3).seat_costs();
+        let canids: Vec<&str> = costs.iter().map(|(canid, _)| canid.as_str()).collect();
+        assert_eq!(canids, ["Z", "Y", "X"]);
+        assert!((costs[0].1 - 0.03).abs() < 1e-12);
+        assert!((costs[1].1 - (2.0 * 1.3 / 40.0 - 0.01)).abs() < 1e-12);
+        assert!((costs[2].1 - 2.0 * 1.525 / 30.0 + 1.3 / 40.0 + 0.01).abs() < 1e-12);
+        assert!(costs.iter().all(|(_, cost)| *cost >= 0.0));
+        assert!(costs.windows(2).all(|pair| pair[0].1 <= pair[1].1));
+    }
+
+    #[test]
+    fn text_format_parses_the_sample() {
+        let text = "
+            # The example in main.
+            A:10 > X,Y
+            B: 20 > X, Z
+
+            C:30 > Y,Z
+            C:50 > Z
+        ";
+        let votelist = parse_text(text).unwrap();
+        assert_eq!(votelist, sample());
+        assert_eq!(
+            seq_phragmen(votelist, 2).outcome(),
+            seq_phragmen(sample(), 2).outcome()
+        );
+        assert_eq!(
+            parse_text("A:10 X").unwrap_err(),
+            PhragmenError::Parse {
+                line: 1,
+                message: "missing '>'".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn elected_candidates_are_never_selected_again() {
+        let options = PhragmenOptions::default();
+        let mut votelist = sample();
+        votelist.push(("D".to_string(), 1.0, vec!["W".to_string()]));
+        let (voters, candidates) = setuplists(votelist);
+        let numcandidates = candidates.len();
+        let mut a = Assignment::new(voters, candidates, None);
+        let first = a.elect_next(&options).unwrap();
+        // Re-electing is a no-op rather than a second seat.
+        let candidate = a.candidates[first].clone();
+        a.elect(&candidate);
+        assert_eq!(a.electedcandidates.len(), 1);
+
+        let mut seen = HashSet::from([first]);
+        while let Some(elected) = a.elect_next(&options) {
+            assert!(seen.insert(elected), "{elected} elected twice");
+            for index in 0..numcandidates {
+                assert_eq!(a.is_elected(index), a.elected_candidates().contains(&index));
+            }
+        }
+        assert_eq!(a.electedcandidates.len(), numcandidates);
+        assert_eq!(a.electionorder.len(), numcandidates);
+    }
+
+    #[test]
+    fn efficiency_metric_tracks_surplus_and_waste() {
+        let ballot = |voterid: &str, budget: f64, canid: &str| {
+            (voterid.to_string(), budget, vec![canid.to_string()])
+        };
+        let balanced = seq_phragmen(vec![ballot("A", 10.0, "X"), ballot("B", 10.0, "Y")], 2);
+        assert_eq!(balanced.efficiency_metric(), 0.0);
+
+        // 20 of the 40 budget is surplus on X beyond what Y needed.
+        let skewed = seq_phragmen(vec![ballot("A", 30.0, "X"), ballot("B", 10.0, "Y")], 2);
+        assert!((skewed.efficiency_metric() - 0.5).abs() < 1e-12);
+
+        // C's 5 elects nobody.
+        let wasteful = seq_phragmen(
+            vec![
+                ballot("A", 10.0, "X"),
+                ballot("B", 10.0, "Y"),
+                ballot("C", 5.0, "W"),
+            ],
+            2,
+        );
+        assert!((wasteful.efficiency_metric() + 0.2).abs() < 1e-12);
+    }
+
+    #[test]
+    fn history_reads_back_a_support_series() {
+        let mut history = ElectionHistory::new();
+        assert!(history.is_empty());
+        let mut votelist = sample();
+        history.push(run_election(votelist.clone(), 2), "jan".to_string());
+        votelist[0] = ("A".to_string(), 100.0, vec!["X".to_string()]);
+        history.push(run_election(votelist.clone(), 2), "feb".to_string());
+        history.push(run_election(votelist, 1), "mar".to_string());
+        assert_eq!(history.len(), 3);
+
+        let series = history.support_series("X");
+        let labels: Vec<&str> = series.iter().map(|(label, _)| label.as_str()).collect();
+        assert_eq!(labels, ["jan", "feb", "mar"]);
+        assert_eq!(series[0].1, None);
+        assert!(series[1].1.unwrap() > 100.0);
+        assert!(series[2].1.unwrap() > 100.0);
+        let z = history.support_series("Z");
+        assert!((z[0].1.unwrap() - 79.23076923076923).abs() < 1e-9);
+        assert!(z[1].1.is_some());
+        assert_eq!(z[2].1, None);
+    }
+
+    #[test]
+    fn entry_threshold_matches_the_second_seat_score() {
+        // After Z, W alone at 1 / budget must beat Y's 1.3 / 40.
+        let backers = ["D".to_string()];
+        let threshold = entry_threshold(sample(), 2, "W", &backers);
+        assert!((threshold - 40.0 / 1.3).abs() < 1e-6);
+        assert!(threshold < 110.0);
+
+        let with_budget = |budget: f64| {
+            let mut votelist = sample();
+            votelist.push(("D".to_string(), budget, vec!["W".to_string()]));
+            winner_ids(&seq_phragmen(votelist, 2)).contains(&"W".to_string())
+        };
+        assert!(with_budget(threshold));
+        assert!(!with_budget(threshold * 0.999));
+        assert_eq!(entry_threshold(sample(), 2, "W", &[]), f64::INFINITY);
+    }
+
+    #[test]
+    fn approval_mode_matches_unit_budgets() {
+        let unit = |mut votelist: VoteList| {
+            for ballot in &mut votelist {
+                ballot.1 = 1.0;
+            }
+            votelist
+        };
+        let approval_mode = PhragmenOptions {
+            approval_mode: true,
+            ..Default::default()
+        };
+        // By budget the whale's X wins; by head count Y does.
+        let votelist = vec![
+            ("A".to_string(), 100.0, vec!["X".to_string()]),
+            ("B".to_string(), 1.0, vec!["Y".to_string()]),
+            ("C".to_string(), 1.0, vec!["Y".to_string()]),
+        ];
+        assert_eq!(winner_ids(&seq_phragmen(votelist.clone(), 1)), ["X"]);
+        let a = seq_phragmen_with(votelist.clone(), 1, &approval_mode);
+        assert_eq!(winner_ids(&a), ["Y"]);
+        assert_eq!(a.outcome(), seq_phragmen(unit(votelist), 1).outcome());
+    }
+
+    #[test]
+    fn approval_filters_see_unit_budgets() {
+        let unit = |mut votelist: VoteList| {
+            for ballot in &mut votelist {
+                ballot.1 = 1.0;
+            }
+            votelist
+        };
+        let approval_mode = PhragmenOptions {
+            approval_mode: true,
+            ..Default::default()
+        };
+        // With the input budgets X (30) would clear a threshold of 2.5, with
+        // unit budgets it has 2.
+        let filtered = PhragmenOptions {
+            min_approval: Some(2.5),
+            ..approval_mode
+        };
+        let unit_filtered = PhragmenOptions {
+            min_approval: Some(2.5),
+            ..Default::default()
+        };
+        let a = seq_phragmen_with(sample(), 2, &filtered);
+        assert!(a.candidate_by_id("X").is_none());
+        assert_eq!(
+            a.outcome(),
+            seq_phragmen_with(unit(sample()), 2, &unit_filtered).outcome()
+        );
+    }
+
+    #[test]
+    fn elections_stream_runs_each_input_lazily() {
+        let inputs: Vec<VoteList> = vec![
+            sample(),
+            vec![("D".to_string(), 5.0, vec!["P".to_string(), "Q".to_string()])],
+            Vec::new(),
+        ];
+        let mut consumed = 0;
+        let stream = elections_stream(inputs.clone().into_iter().inspect(|_| consumed += 1), 2);
+        let outcomes: Vec<Outcome> = stream.collect();
+        assert_eq!(consumed, 3);
+        let expected: Vec<Outcome> = inputs
+            .into_iter()
+            .map(|votelist| run_election(votelist, 2))
+            .collect();
+        assert_eq!(outcomes, expected);
+        assert!(outcomes[2].winners.is_empty());
+
+        let mut endless = elections_stream(std::iter::repeat_with(sample), 1);
+        for _ in 0..3 {
+            assert_eq!(endless.next().unwrap().winners[0].0, "Z");
+        }
+    }
+
+    #[test]
+    fn withdrawn_winner_is_replaced_by_the_next_candidate() {
+        let options = PhragmenOptions::default();
+        let (voters, candidates) = setuplists(sample());
+        let mut a = Assignment::new(voters, candidates, None);
+        a.elect_next(&options).unwrap();
+        a.elect_next(&options).unwrap();
+        assert_eq!(winner_ids(&a), ["Z", "Y"]);
+
+        assert!(a.withdraw("Y"));
+        assert!(!a.withdraw("W"));
+        assert_eq!(winner_ids(&a), ["Z"]);
+        // A backed only Y among the winners, so its load is released.
+        assert_eq!(a.voter_load_of("A"), Some(0.0));
+
+        let replacement = a.elect_next(&options).unwrap();
+        assert_eq!(a.candidates[replacement].canid, "X");
+        assert!(a.elect_next(&options).is_none());
+        a.loadstoweights();
+
+        let mut without_y = sample();
+        for ballot in &mut without_y {
+            ballot.2.retain(|canid| canid != "Y");
+        }
+        let expected = seq_phragmen(without_y, 2);
+        assert_eq!(a.outcome().winners, expected.outcome().winners);
+    }
+
+    #[test]
+    fn fingerprint_ignores_round_off_and_winner_order() {
+        let outcome = run_election(sample(), 2);
+        let mut nudged = outcome.clone();
+        nudged.winners[0].1 += 1e-10;
+        nudged.winners[1].1 -= 1e-10;
+        nudged.winners.reverse();
+        nudged.budgets.clear();
+        assert_eq!(nudged.fingerprint(), outcome.fingerprint());
+
+        let mut changed = outcome.clone();
+        changed.winners[0].1 += 1e-3;
+        assert_ne!(changed.fingerprint(), outcome.fingerprint());
+        let mut renamed = outcome.clone();
+        renamed.winners[0].0 = "W".to_string();
+        assert_ne!(renamed.fingerprint(), outcome.fingerprint());
+
+        let zero = |support: f64| Outcome {
+            winners: vec![("X".to_string(), support)],
+            assignments: Vec::new(),
+            budgets: Vec::new(),
+        };
+        assert_eq!(zero(-1e-12).fingerprint(), zero(0.0).fingerprint());
+    }
+
+    #[test]
+    fn lowest_initial_score_is_the_most_approved_candidate() {
+        let scores = initial_scores(sample());
+        let canids: Vec<&str> = scores.iter().map(|(canid, _)| canid.as_str()).collect();
+        assert_eq!(canids, ["Z", "Y", "X"]);
+        assert_eq!(scores[0].1, 1.0 / 100.0);
+        assert_eq!(scores[2].1, 1.0 / 30.0);
+
+        // Approval is budget, not head count: P's single backer outweighs
+        // Q's three.
+        let votelist = vec![
+            ("A".to_string(), 50.0, vec!["P".to_string()]),
+            ("B".to_string(), 10.0, vec!["Q".to_string()]),
+            ("C".to_string(), 10.0, vec!["Q".to_string()]),
+            ("D".to_string(), 10.0, vec!["Q".to_string()]),
+        ];
+        assert_eq!(
+            initial_scores(votelist),
+            [("P".to_string(), 1.0 / 50.0), ("Q".to_string(), 1.0 / 30.0)]
+        );
+    }
+
+    #[test]
+    fn larger_quota_keeps_a_candidate_out() {
+        let quota = |canid: &str, quota: f64| PhragmenOptions {
+            candidate_quota: HashMap::from([(canid.to_string(), quota)]),
+            ..Default::default()
+        };
+        assert_eq!(winner_ids(&seq_phragmen(sample(), 2)), ["Z", "Y"]);
+        // Y's second-round score doubles to 0.065, behind X's 0.04.
+        let a = seq_phragmen_with(sample(), 2, &quota("Y", 2.0));
+        assert_eq!(winner_ids(&a), ["Z", "X"]);
+        // Z drops from first to second.
+        let a = seq_phragmen_with(sample(), 3, &quota("Z", 3.0));
+        assert_eq!(winner_ids(&a)[0], "Y");
+
+        for ignored in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            let a = seq_phragmen_with(sample(), 2, &quota("Y", ignored));
+            assert_eq!(a.outcome(), seq_phragmen(sample(), 2).outcome());
+        }
+    }
+
+    #[test]
+    fn conversion_roundtrip_holds_once_balanced() {
+        let mut a = seq_phragmen(sample(), 2);
+        a.reach_equilibrium(100, 1e-12);
+        assert!(a.check_conversion_roundtrip(1e-9));
+    }
+
+    #[cfg(feature = "rational")]
+    #[test]
+    fn rational_run_elects_the_float_winners_exactly() {
+        use num_rational::BigRational;
+        use num_traits::ToPrimitive;
+
+        let exact = |value: f64| BigRational::from_float(value).unwrap();
+        let votelist = sample()
+            .into_iter()
+            .map(|(voterid, budget, approvals)| (voterid, exact(budget), approvals))
+            .collect();
+        let winners = seq_phragmen_rational(votelist, 2);
+        let float = run_election(sample(), 2).winners;
+        let canids: Vec<&str> = winners.iter().map(|(canid, _)| canid.as_str()).collect();
+        assert_eq!(canids, ["Z", "Y"]);
+        assert_eq!(winners[0].1, exact(1030.0) / exact(13.0));
+        assert_eq!(winners[1].1, exact(400.0) / exact(13.0));
+        for ((canid, support), (floatid, floatsupport)) in winners.iter().zip(&float) {
+            assert_eq!(canid, floatid);
+            assert!((support.to_f64().unwrap() - floatsupport).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn candidates_tied_for_the_last_seat_are_contenders() {
+        // X, Y and V tie for the second seat; X takes it on the index.
+        let votelist = vec![
+            ("A".to_string(), 1.0, vec!["X".to_string()]),
+            ("B".to_string(), 1.0, vec!["Y".to_string()]),
+            ("E".to_string(), 1.0, vec!["V".to_string()]),
+            ("C".to_string(), 2.0, vec!["Z".to_string()]),
+            ("D".to_string(), 0.5, vec!["W".to_string()]),
+        ];
+        let a = seq_phragmen(votelist, 2);
+        assert_eq!(winner_ids(&a), ["Z", "X"]);
+        let contenders = a.last_seat_contenders(1e-9);
+        assert_eq!(contenders, [("V".to_string(), 1.0), ("Y".to_string(), 1.0)]);
+        // W's 2.0 is within 1.5 of X's 1.0; the winners never are listed.
+        assert_eq!(
+            a.last_seat_contenders(1.5),
+            [
+                ("V".to_string(), 1.0),
+                ("Y".to_string(), 1.0),
+                ("W".to_string(), 2.0)
+            ]
+        );
+        assert!(seq_phragmen(sample(), 0)
+            .last_seat_contenders(1.0)
+            .is_empty());
+    }
+
+    #[test]
+    fn election_extended_in_two_steps_runs_like_the_sample() {
+        let ballots = sample();
+        let mut election = Election::new();
+        election.extend(ballots[..2].iter().cloned());
+        election.extend(ballots[2..].iter().cloned());
+        assert_eq!(election.votelist(), ballots.as_slice());
+        assert_eq!(
+            election.run(2).unwrap().outcome(),
+            seq_phragmen(sample(), 2).outcome()
+        );
+
+        // Merging folds the two C ballots into one voter.
+        let mut merged = Election::new().with_duplicates(DuplicateVoters::Merge);
+        merged.extend(ballots[..3].iter().cloned());
+        merged.extend([(
+            "C".to_string(),
+            50.0,
+            vec!["Z".to_string(), "Z".to_string()],
+        )]);
+        assert_eq!(merged.votelist().len(), 3);
+        assert_eq!(
+            merged.votelist()[2],
+            (
+                "C".to_string(),
+                80.0,
+                vec!["Y".to_string(), "Z".to_string()]
+            )
+        );
+        assert_eq!(merged.run(2).unwrap().electedcandidates.len(), 2);
+
+        let mut invalid = Election::new();
+        invalid.extend([("A".to_string(), -1.0, vec!["X".to_string()])]);
+        assert!(invalid.run(1).is_err());
+    }
+
+    #[test]
+    fn edge_list_csv_round_trip_keeps_the_winners() {
+        let mut votelist = sample();
+        votelist[3].0 = "E".to_string();
+        let a = seq_phragmen(votelist.clone(), 2);
+        let mut csv = Vec::new();
+        a.write_edgelist_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert!(csv.starts_with("source,target,weight\n"));
+        assert_eq!(csv.lines().count(), 1 + a.edgelist.len());
+
+        let restored = read_edgelist_csv(&csv).unwrap();
+        assert_eq!(restored, votelist);
+        assert_eq!(winner_ids(&seq_phragmen(restored, 2)), winner_ids(&a));
+
+        // Without weights every voter counts equally.
+        let unweighted = read_edgelist_csv("A,X\nA,Y\nB,Y\n").unwrap();
+        assert_eq!(
+            unweighted,
+            [
+                ("A".to_string(), 1.0, vec!["X".to_string(), "Y".to_string()]),
+                ("B".to_string(), 1.0, vec!["Y".to_string()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn include_only_restricts_the_winners() {
+        let mut options = PhragmenOptions {
+            include_only: Some(HashSet::from(["X".to_string(), "Y".to_string()])),
+            ..Default::default()
+        };
+        // Y has 40 approval to X's 30; the ballot approving only Z goes.
+        let a = seq_phragmen_with(sample(), 1, &options);
+        assert_eq!(winner_ids(&a), ["Y"]);
+        assert_eq!(a.voterlist.len(), 3);
+        assert_eq!(a.candidates.len(), 2);
+        let a = seq_phragmen_with(sample(), 3, &options);
+        assert_eq!(winner_ids(&a), ["Y", "X"]);
+
+        // Exclusion is applied on top of the allowlist.
+        options.exclude = HashSet::from(["Y".to_string()]);
+        let a = seq_phragmen_with(sample(), 3, &options);
+        assert_eq!(winner_ids(&a), ["X"]);
+        assert_eq!(a.voterlist.len(), 2);
+    }
+
+    #[test]
+    fn oscillation_is_detected_on_synthetic_changes() {
+        let pingpong: Vec<f64> = (0..OSCILLATION_WINDOW)
+            .map(|sweep| if sweep % 2 == 0 { 0.5 } else { 0.25 })
+            .collect();
+        assert_eq!(oscillation_period(&pingpong), Some(2));
+        let cycle: Vec<f64> = (0..OSCILLATION_WINDOW + 3)
+            .map(|sweep| [0.3, 0.1, 0.2][sweep % 3])
+            .collect();
+        assert_eq!(oscillation_period(&cycle), Some(3));
+        assert_eq!(oscillation_period(&[0.1; OSCILLATION_WINDOW]), Some(1));
+
+        // Shrinking changes are converging, and short histories say nothing.
+        let shrinking: Vec<f64> = (0..OSCILLATION_WINDOW)
+            .map(|sweep| 0.5f64.powi(sweep as i32))
+            .collect();
+        assert_eq!(oscillation_period(&shrinking), None);
+        assert_eq!(oscillation_period(&pingpong[1..]), None);
+        // Neither shrinking nor periodic.
+        let noisy = [0.5, 0.3, 0.4, 0.2, 0.35, 0.1, 0.3, 0.05];
+        assert_eq!(oscillation_period(&noisy[..OSCILLATION_WINDOW]), None);
+
+        let mut a = seq_phragmen(sample(), 2);
+        let status = a.reach_equilibrium(1000, 1e-9);
+        assert!(matches!(status, Equilibrium::Converged { .. }));
+    }
+
+    #[test]
+    fn backing_only_winners_beats_backing_a_loser() {
+        let mut votelist = sample();
+        votelist.push(("D".to_string(), 5.0, vec!["W".to_string()]));
+        let a = seq_phragmen(votelist, 2);
+        assert_eq!(winner_ids(&a), ["Z", "Y"]);
+        let satisfaction: Vec<f64> = a
+            .voter_satisfaction()
+            .into_iter()
+            .map(|(_, satisfaction)| satisfaction)
+            .collect();
+        // The last C backs only Z, the best supported winner.
+        assert!((satisfaction[3] - 1.0).abs() < 1e-12);
+        // A's budget all lands on Y, weighted by Y's support against Z's.
+        let expected = a.outcome().winners[1].1 / a.outcome().winners[0].1;
+        assert!((satisfaction[0] - expected).abs() < 1e-12);
+        assert_eq!(satisfaction[4], 0.0);
+        assert!(satisfaction[3] > satisfaction[0]);
+        assert!(satisfaction[0] > satisfaction[4]);
+        assert!(satisfaction.iter().all(|s| (0.0..=1.0).contains(s)));
+    }
+
+    #[test]
+    fn bounded_run_rejects_oversize_input_and_matches_within_bounds() {
+        // Exactly at the bounds and well within them.
+        for numtoelect in 1..=3 {
+            assert_eq!(
+                seq_phragmen_bounded::<3, 4>(&sample(), numtoelect).unwrap(),
+                run_election(sample(), numtoelect).winners
+            );
+        }
+        assert_eq!(
+            seq_phragmen_bounded::<8, 16>(&sample(), 2).unwrap(),
+            run_election(sample(), 2).winners
+        );
+        assert_eq!(
+            seq_phragmen_bounded::<3, 3>(&sample(), 2),
+            Err(PhragmenError::TooManyVoters {
+                limit: 3,
+                voters: 4
+            })
+        );
+        assert!(matches!(
+            seq_phragmen_bounded::<2, 4>(&sample(), 2),
+            Err(PhragmenError::TooManyCandidates { limit: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn load_deltas_add_up_to_the_final_loads() {
+        let options = PhragmenOptions {
+            record_load_deltas: true,
+            ..Default::default()
+        };
+        let a = seq_phragmen_with(sample(), 3, &options);
+        // Z touches its three edges, Y two and X two.
+        let touched: Vec<usize> = a.load_deltas().iter().map(Vec::len).collect();
+        assert_eq!(touched, [3, 2, 2]);
+        let mut totals = vec![0.0; a.edgelist.len()];
+        for round in a.load_deltas() {
+            for &(edgeindex, delta) in round {
+                totals[edgeindex] += delta;
+            }
+        }
+        for (total, load) in totals.iter().zip(a.edge_loads()) {
+            assert!((total - load).abs() < 1e-12);
+        }
+        assert!(seq_phragmen(sample(), 2).load_deltas().is_empty());
+    }
+
+    #[test]
+    fn core_check_flags_an_unrepresented_quota() {
+        let votelist = vec![
+            ("A".to_string(), 50.0, vec!["X".to_string()]),
+            ("B".to_string(), 60.0, vec!["Y".to_string()]),
+            ("C".to_string(), 10.0, vec!["W".to_string()]),
+        ];
+        let winners = winner_ids(&seq_phragmen(votelist.clone(), 2));
+        assert_eq!(winners, ["Y", "X"]);
+        assert_eq!(is_in_core(&votelist, &winners, 2), Ok(true));
+        // B holds the full quota of 60 and gets nothing.
+        let blocked = ["X".to_string(), "W".to_string()];
+        assert_eq!(is_in_core(&votelist, &blocked, 2), Ok(false));
+        // With three seats the quota drops to 40 and A blocks as well.
+        assert_eq!(is_in_core(&votelist, &["Y".to_string()], 3), Ok(false));
+
+        let invalid = vec![("A".to_string(), f64::NAN, vec!["X".to_string()])];
+        assert!(is_in_core(&invalid, &[], 1).is_err());
+    }
+
+    #[test]
+    fn core_check_flags_a_better_single_replacement() {
+        // A holds two thirds of the budget, so two of three seats, but gets
+        // only Q. Everyone is represented, so justified representation holds.
+        let votelist = vec![
+            (
+                "A".to_string(),
+                60.0,
+                vec!["P".to_string(), "Q".to_string()],
+            ),
+            (
+                "B".to_string(),
+                30.0,
+                vec!["S".to_string(), "U".to_string()],
+            ),
+        ];
+        let winners = ["Q".to_string(), "S".to_string(), "U".to_string()];
+        // Putting P in U's place, A pays the two quotas of P and Q itself.
+        assert_eq!(is_in_core(&votelist, &winners, 3), Ok(false));
+        let fair = ["P".to_string(), "Q".to_string(), "S".to_string()];
+        assert_eq!(is_in_core(&votelist, &fair, 3), Ok(true));
+        assert_eq!(
+            winner_ids(&seq_phragmen(votelist.clone(), 3)),
+            ["P", "Q", "S"]
+        );
+        // Over-serving B leaves A with one seat where it can pay for two.
+        let generous = ["P".to_string(), "S".to_string(), "U".to_string()];
+        assert_eq!(is_in_core(&votelist, &generous, 3), Ok(false));
+    }
+
+    #[test]
+    fn integer_weights_add_up_per_voter_and_in_total() {
+        let mut votelist = sample();
+        votelist[3].0 = "E".to_string();
+        votelist.push(("D".to_string(), 7.0, vec!["W".to_string()]));
+        let a = seq_phragmen(votelist, 2);
+        let total = 1_000_003;
+        let rows = a.round_weights_integer(total);
+        assert!(rows.iter().all(|(voterid, _, _)| voterid != "D"));
+        assert_eq!(rows.iter().map(|row| row.2).sum::<u128>(), total);
+
+        let shares = largest_remainder(total, &[10.0, 20.0, 30.0, 50.0]);
+        for (voterid, share) in ["A", "B", "C", "E"].into_iter().zip(shares) {
+            let units: u128 = rows
+                .iter()
+                .filter(|row| row.0 == voterid)
+                .map(|row| row.2)
+                .sum();
+            assert_eq!(units, share, "voter {voterid}");
+        }
+    }
+
+    /// `numvoters` ballots over candidates `c0`.. `c{numcandidates - 1}`,
+    /// each with a budget from 1 to 100 and up to `maxapprovals` distinct
+    /// approvals.
+    fn random_votelist(
+        seed: u64,
+        numvoters: usize,
+        numcandidates: usize,
+        maxapprovals: usize,
+    ) -> VoteList {
+        let mut state = seed;
+        (0..numvoters)
+            .map(|voter| {
+                let budget = (splitmix64(&mut state) % 100 + 1) as f64;
+                let count = splitmix64(&mut state) as usize % maxapprovals + 1;
+                let mut approvals: Vec<String> = Vec::with_capacity(count);
+                for _ in 0..count {
+                    let canid = format!("c{}", splitmix64(&mut state) as usize % numcandidates);
+                    if !approvals.contains(&canid) {
+                        approvals.push(canid);
+                    }
+                }
+                (format!("v{voter}"), budget, approvals)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn incremental_rescoring_matches_a_full_rescore() {
+        let full = PhragmenOptions {
+            trace: true,
+            ..Default::default()
+        };
+        let incremental = PhragmenOptions {
+            incremental_rescoring: true,
+            ..full.clone()
+        };
+        // Every round's scores agree to the bit, not just the winners.
+        for seed in 0..3 {
+            let votelist = random_votelist(seed, 60, 25, 5);
+            let expected = seq_phragmen_with(votelist.clone(), 8, &full);
+            let a = seq_phragmen_with(votelist, 8, &incremental);
+            assert_eq!(a.trace(), expected.trace(), "seed {seed}");
+            assert_eq!(a.outcome(), expected.outcome(), "seed {seed}");
+        }
+
+        // Budgets set and a winner withdrawn between rounds move approvals
+        // and loads away from the last winner's backers: D's raise makes W,
+        // which shares no voter with Z, the next winner.
+        let mut votelist = sample();
+        votelist.push(("D".to_string(), 5.0, vec!["W".to_string()]));
+        let run = |options: &PhragmenOptions| {
+            let (voters, candidates) = setuplists(votelist.clone());
+            let mut a = Assignment::new(voters, candidates, None);
+            a.elect_next(options);
+            a.set_budgets(&[10.0, 20.0, 30.0, 50.0, 100.0]);
+            a.elect_next(options);
+            a.withdraw("Z");
+            a.elect_next(options);
+            a
+        };
+        let expected = run(&full);
+        assert_eq!(expected.trace()[1].elected, "W");
+        let a = run(&incremental);
+        assert_eq!(a.electionorder, expected.electionorder);
+        assert_eq!(a.trace(), expected.trace());
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn parquet_tables_read_back_with_matching_row_counts() {
+        use parquet::file::reader::{FileReader, SerializedFileReader};
+
+        let outcome = run_election(sample(), 2);
+        let numedges: usize = outcome.assignments.iter().map(|(_, e)| e.len()).sum();
+        let (mut assignments, mut winners) = (Vec::new(), Vec::new());
+        outcome
+            .write_parquet(&mut assignments, &mut winners)
+            .unwrap();
+        for (table, bytes, rows) in [
+            ("assignments", assignments, numedges),
+            ("winners", winners, outcome.winners.len()),
+        ] {
+            let path = std::env::temp_dir().join(format!(
+                "seq-phragmen-{}-{table}.parquet",
+                std::process::id()
+            ));
+            std::fs::write(&path, bytes).unwrap();
+            let reader = SerializedFileReader::new(std::fs::File::open(&path).unwrap());
+            std::fs::remove_file(&path).unwrap();
+            let numrows = reader.unwrap().metadata().file_metadata().num_rows();
+            assert_eq!(numrows as usize, rows, "{table}");
+        }
+    }
+
+    #[test]
+    fn live_election_matches_independent_runs() {
+        let ballots = sample();
+        let mut live = LiveElection::new();
+        for count in 1..=3 {
+            live.add_vote(ballots[count - 1].clone());
+            let expected = winner_ids(&seq_phragmen(ballots[..count].to_vec(), 2));
+            assert_eq!(
+                live.current_winners(2),
+                expected.as_slice(),
+                "{count} votes"
+            );
+        }
+        // Asking for fewer seats reruns even without new votes.
+        assert_eq!(live.current_winners(1), ["Z"]);
+        live.add_vote(("E".to_string(), 100.0, vec!["X".to_string()]));
+        assert_eq!(live.current_winners(1), ["X"]);
+    }
+
+    #[test]
+    fn approval_inversion_is_reported() {
+        // Y shares all its backers with X, so after X it scores 2/20 and
+        // loses to Z's 1/15 despite more approval.
+        let votelist = vec![
+            (
+                "A".to_string(),
+                10.0,
+                vec!["X".to_string(), "Y".to_string()],
+            ),
+            (
+                "B".to_string(),
+                10.0,
+                vec!["X".to_string(), "Y".to_string()],
+            ),
+            ("C".to_string(), 15.0, vec!["Z".to_string()]),
+        ];
+        let a = seq_phragmen(votelist, 2);
+        assert_eq!(winner_ids(&a), ["X", "Z"]);
+        assert_eq!(
+            a.approval_inversions(),
+            [("Z".to_string(), "Y".to_string())]
+        );
+        assert!(seq_phragmen(sample(), 2).approval_inversions().is_empty());
+    }
+
+    #[test]
+    fn time_limit_cuts_a_large_election_short() {
+        use std::time::Duration;
+
+        // 5,000 voters approving 6 of 200 candidates each.
+        let votelist: VoteList = (0..5_000)
+            .map(|voter| {
+                let approvals = (0..6)
+                    .map(|k| format!("c{}", (voter * 7 + k * 31) % 200))
+                    .collect();
+                (format!("v{voter}"), 1.0 + (voter % 11) as f64, approvals)
+            })
+            .collect();
+        let (a, timed_out) = seq_phragmen_timed_limit(votelist.clone(), 50, Duration::ZERO);
+        assert!(timed_out);
+        assert!(a.electedcandidates.len() < 50);
+
+        let (a, timed_out) = seq_phragmen_timed_limit(votelist.clone(), 5, Duration::MAX);
+        assert!(!timed_out);
+        assert_eq!(a.outcome(), seq_phragmen(votelist, 5).outcome());
+    }
+
+    #[test]
+    fn pool_of_two_voters_acts_as_one() {
+        let mut votelist = sample();
+        votelist.push(("D".to_string(), 5.0, vec!["W".to_string()]));
+        let options = PhragmenOptions {
+            pools: vec![(
+                "P".to_string(),
+                25.0,
+                vec!["A".to_string(), "D".to_string()],
+            )],
+            ..Default::default()
+        };
+        let pooled = seq_phragmen_with(votelist, 3, &options);
+
+        let mut merged = sample();
+        merged[0] = (
+            "P".to_string(),
+            25.0,
+            vec!["X".to_string(), "Y".to_string(), "W".to_string()],
+        );
+        let single = seq_phragmen(merged, 3);
+        assert_eq!(pooled.votelist(), single.votelist());
+        assert_eq!(pooled.outcome(), single.outcome());
+    }
+
+    #[test]
+    fn support_cap_moves_weight_to_the_other_winner() {
+        let votelist = vec![
+            (
+                "A".to_string(),
+                80.0,
+                vec!["X".to_string(), "Y".to_string()],
+            ),
+            ("B".to_string(), 20.0, vec!["X".to_string()]),
+            ("C".to_string(), 10.0, vec!["Y".to_string()]),
+        ];
+        let uncapped = seq_phragmen(votelist.clone(), 2);
+        let x = uncapped.candidate_by_id("X").unwrap().index;
+        let y = uncapped.candidate_by_id("Y").unwrap().index;
+        assert!((uncapped.cansupport[x] - 60.0).abs() < 1e-9);
+        assert!((uncapped.cansupport[y] - 50.0).abs() < 1e-9);
+
+        let options = PhragmenOptions {
+            max_support: Some(55.0),
+            ..Default::default()
+        };
+        let capped = seq_phragmen_with(votelist, 2, &options);
+        assert!((capped.cansupport[x] - 55.0).abs() < 1e-9);
+        // A's 40 on X is cut by 5/60 and moves to Y; B has nowhere to go.
+        let moved = 40.0 * 5.0 / 60.0;
+        assert!((capped.cansupport[y] - 50.0 - moved).abs() < 1e-9);
+        for edge in &capped.edgelist {
+            let support = capped.cansupport[edge.canindex()];
+            if support > 0.0 {
+                let load = capped.edgeweight[edge.index()] / support;
+                assert!((capped.edgeload[edge.index()] - load).abs() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn trace_covers_every_round_and_ends_on_the_final_loads() {
+        let options = PhragmenOptions {
+            trace: true,
+            ..Default::default()
+        };
+        let a = seq_phragmen_with(sample(), 3, &options);
+        let trace = a.trace();
+        assert_eq!(trace.len(), 3);
+        for (round, canid) in trace.iter().zip(winner_ids(&a)) {
+            assert_eq!(round.elected, canid);
+            assert_eq!(round.approvals, a.approvals());
+        }
+        for &(edgeindex, load) in &trace[2].load_updates {
+            assert_eq!(a.edge_loads()[edgeindex], load);
+        }
+        let mut replayed = vec![0.0; a.edgelist.len()];
+        for round in trace {
+            for &(edgeindex, load) in &round.load_updates {
+                replayed[edgeindex] = load;
+            }
+        }
+        assert_eq!(replayed, a.edge_loads());
+        assert!(seq_phragmen(sample(), 2).trace().is_empty());
+    }
+
+    /// One voter approving `c0` to `c9`, so every candidate ties.
+    fn ten_way_tie() -> VoteList {
+        vec![(
+            "A".to_string(),
+            1.0,
+            (0..10).map(|canindex| format!("c{canindex}")).collect(),
+        )]
+    }
+
+    fn random_tie_winners(seed: u64) -> Vec<(String, f64)> {
+        let options = PhragmenOptions {
+            tie_break: TieBreak::Random { seed },
+            ..Default::default()
+        };
+        seq_phragmen_with(ten_way_tie(), 3, &options)
+            .outcome()
+            .winners
+    }
+
+    #[test]
+    fn random_tie_break_repeats_for_the_same_seed() {
+        for seed in [0, 1, 42, u64::MAX] {
+            assert_eq!(random_tie_winners(seed), random_tie_winners(seed));
+        }
+    }
+
+    #[test]
+    fn random_tie_break_varies_with_the_seed() {
+        let firsts: HashSet<String> = (0..20)
+            .map(|seed| random_tie_winners(seed)[0].0.clone())
+            .collect();
+        assert!(firsts.len() > 1);
+    }
+
+    #[test]
+    fn comfortable_winner_needs_a_positive_withdrawal() {
+        let a = seq_phragmen(sample(), 2);
+        // Z's backers outside Z carry 30 * (1.3 / 40 - 1 / 100) of load, and
+        // Z must be pushed past Y's winning score of 1.3 / 40.
+        let denominator = 1.3 / 40.0 * 100.0 - 30.0 * (1.3 / 40.0 - 0.01);
+        let expected = 100.0 - 100.0 / denominator;
+        let withdrawal = a.withdrawal_to_unseat("Z");
+        assert!(withdrawal > 0.0);
+        assert!((withdrawal - expected).abs() < 1e-9);
+        assert_eq!(a.withdrawal_to_unseat("Y"), 0.0);
+        assert_eq!(a.withdrawal_to_unseat("X"), 0.0);
+        assert_eq!(a.withdrawal_to_unseat("W"), 0.0);
+    }
+
+    #[test]
+    fn construction_errors_point_at_the_offending_row() {
+        let mut votelist = sample();
+        votelist[2].1 = -30.0;
+        assert_eq!(
+            try_setuplists(votelist).unwrap_err(),
+            PhragmenError::InvalidBudget {
+                index: 2,
+                voterid: "C".to_string(),
+                budget: -30.0
+            }
+        );
+
+        let mut votelist = sample();
+        votelist[3].2.push("Z".to_string());
+        assert_eq!(
+            try_setuplists(votelist).unwrap_err(),
+            PhragmenError::DuplicateApproval {
+                index: 3,
+                voterid: "C".to_string(),
+                canid: "Z".to_string()
+            }
+        );
+
+        let borrowed = vec![
+            ("A", 1.0, vec!["X"]),
+            ("B", 1.0, vec![]),
+            ("C", 0.0, vec![]),
+        ];
+        assert!(matches!(
+            VoteSet::try_from(borrowed),
+            Err(PhragmenError::InvalidBudget { index: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn normalizer_collapses_candidate_aliases() {
+        let votelist = vec![
+            (
+                "A".to_string(),
+                10.0,
+                vec!["x ".to_string(), "X".to_string()],
+            ),
+            (
+                " B".to_string(),
+                20.0,
+                vec!["X".to_string(), "y".to_string()],
+            ),
+            ("b".to_string(), 5.0, vec![" Y".to_string()]),
+        ];
+        let normalize = |id: &str| id.trim().to_lowercase();
+        let (voters, candidates) = setuplists_normalized(votelist.clone(), normalize, false);
+        let canids: Vec<&str> = candidates.iter().map(|c| c.canid.as_str()).collect();
+        assert_eq!(canids, ["x", "y"]);
+        // The repeated approval within A's ballot is dropped.
+        assert_eq!(voters[0].edges.len(), 1);
+        assert_eq!(voters[1].voterid, " B");
+        let a = Assignment::new(voters, candidates, None);
+        assert_eq!(a.approvals(), [30.0, 25.0]);
+
+        let (voters, _) = setuplists_normalized(votelist, normalize, true);
+        assert_eq!(voters[1].voterid, "b");
+        assert_eq!(voters[2].voterid, "b");
+    }
+
+    #[test]
+    fn properly_run_outcomes_leave_no_complaints() {
+        // Y misses the only seat by a hair.
+        let votelist = vec![
+            ("A".to_string(), 10.0, vec!["X".to_string()]),
+            ("B".to_string(), 10.0 - 1e-9, vec!["Y".to_string()]),
+        ];
+        let a = seq_phragmen(votelist, 1);
+        assert_eq!(winner_ids(&a), ["X"]);
+        assert!(a.complaint("Y").is_none());
+        let a = seq_phragmen(sample(), 2);
+        for candidate in &a.candidates {
+            assert!(a.complaint(&candidate.canid).is_none());
+        }
+
+        // Y claims a winning score above what X's backers would reach.
+        let mut a = seq_phragmen(sample(), 2);
+        let y = a.candidate_by_id("Y").unwrap().index;
+        a.canscore[y] = 0.06;
+        let complaint = a.complaint("X").unwrap();
+        let load = (1.0 + 10.0 * 1.3 / 40.0 + 20.0 / 100.0) / 30.0;
+        assert!((complaint.load - load).abs() < 1e-12);
+        assert_eq!(complaint.max_winner_load, 0.06);
+        assert_eq!(complaint.voters, ["A", "B"]);
+    }
+
+    #[test]
+    fn streamed_approval_matches_the_full_build() {
+        // A repeated voter id and a candidate listed twice on one ballot.
+        let mut votelist = sample();
+        votelist.push(("A".to_string(), 4.0, vec!["W".to_string()]));
+        votelist.push((
+            "dup".to_string(),
+            2.0,
+            vec!["X".to_string(), "X".to_string()],
+        ));
+        let streamed = stream_approval(votelist.clone().into_iter());
+        let (voters, candidates) = setuplists(votelist);
+        let a = Assignment::new(voters, candidates, None);
+        assert_eq!(streamed.len(), a.candidates.len());
+        for candidate in &a.candidates {
+            assert_eq!(streamed[&candidate.canid], a.approvals()[candidate.index]);
+        }
+        assert_eq!(streamed["W"], 4.0);
+    }
+
+    #[test]
+    fn min_backers_blocks_a_single_whale() {
+        let mut votelist = vec![("whale".to_string(), 100.0, vec!["W".to_string()])];
+        for voterid in ["A", "B", "C"] {
+            votelist.push((voterid.to_string(), 10.0, vec!["X".to_string()]));
+        }
+        assert_eq!(winner_ids(&seq_phragmen(votelist.clone(), 1)), ["W"]);
+        let options = PhragmenOptions {
+            min_backers: 2,
+            ..Default::default()
+        };
+        assert_eq!(
+            winner_ids(&seq_phragmen_with(votelist.clone(), 1, &options)),
+            ["X"]
+        );
+        // W never qualifies, so the second seat stays empty.
+        assert_eq!(
+            winner_ids(&seq_phragmen_with(votelist.clone(), 2, &options)),
+            ["X"]
+        );
+
+        // Two ballots under the same id are still one backer.
+        votelist.push(("whale".to_string(), 1.0, vec!["W".to_string()]));
+        assert_eq!(winner_ids(&seq_phragmen_with(votelist, 1, &options)), ["X"]);
+    }
+
+    #[test]
+    fn precision_audit_agrees_unless_round_off_decides() {
+        let report = precision_audit(sample(), 2);
+        assert!(report.agrees());
+        assert_eq!(report.f64_winners, ["Y", "Z"]);
+        assert_eq!(report.f32_winners, report.f64_winners);
+        #[cfg(feature = "rational")]
+        assert_eq!(report.rational_winners.as_ref(), Some(&report.f64_winners));
+
+        // In f32 both budgets round to 1.0 and the tie goes to X.
+        let votelist = vec![
+            ("A".to_string(), 1.0, vec!["X".to_string()]),
+            ("B".to_string(), 1.0 + 1e-12, vec!["Y".to_string()]),
+        ];
+        let report = precision_audit(votelist, 1);
+        assert!(!report.agrees());
+        assert_eq!(report.f32_winners, ["X"]);
+        assert_eq!(report.f64_winners, ["Y"]);
+        assert_eq!(report.differing, ["X", "Y"]);
+    }
+
+    #[test]
+    fn constrained_run_reports_truncation() {
+        let options = PhragmenOptions {
+            support_floor: Some(50.0),
+            ..Default::default()
+        };
+        // Only Z clears the floor.
+        let a = seq_phragmen_with(sample(), 3, &options);
+        assert_eq!(a.seats_filled(), 1);
+        assert!(a.was_truncated(3));
+        assert!(!a.was_truncated(1));
+
+        let a = seq_phragmen(sample(), 3);
+        assert_eq!(a.seats_filled(), 3);
+        assert!(!a.was_truncated(3));
+        assert!(a.was_truncated(4));
+    }
+
+    #[test]
+    fn per_winner_weights_sum_to_the_support() {
+        let a = seq_phragmen(sample(), 3);
+        let grouped = a.assignments_by_winner();
+        let winners: Vec<String> = grouped.iter().map(|(canid, _)| canid.clone()).collect();
+        assert_eq!(winners, winner_ids(&a));
+        for (canid, backers) in grouped {
+            assert!(backers.iter().all(|(_, weight)| *weight > 0.0));
+            let total: f64 = backers.iter().map(|(_, weight)| weight).sum();
+            let support = a.cansupport[a.candidate_by_id(&canid).unwrap().index];
+            assert!((total - support).abs() < 1e-9 * support);
+        }
+    }
+
+    #[test]
+    fn voter_edges_are_sorted_and_order_does_not_matter() {
+        // D lists its approvals against the candidate order.
+        let mut votelist = sample();
+        votelist.push((
+            "D".to_string(),
+            15.0,
+            vec!["Z".to_string(), "Y".to_string(), "X".to_string()],
+        ));
+        let (voters, _) = setuplists(votelist.clone());
+        for voter in &voters {
+            assert!(voter
+                .edges
+                .windows(2)
+                .all(|pair| pair[0].canindex() < pair[1].canindex()));
+        }
+
+        let mut reordered = votelist.clone();
+        for ballot in &mut reordered {
+            ballot.2.reverse();
+        }
+        for numtoelect in 1..=3 {
+            assert_eq!(
+                seq_phragmen(reordered.clone(), numtoelect)
+                    .outcome()
+                    .winners,
+                seq_phragmen(votelist.clone(), numtoelect).outcome().winners
+            );
+        }
+    }
+
+    #[test]
+    fn rounded_outcome_keeps_the_full_precision_original() {
+        let outcome = run_election(sample(), 2);
+        let rounded = outcome.rounded(2);
+        assert_eq!(rounded.winners[0], ("Z".to_string(), 79.23));
+        assert_eq!(rounded.winners[1], ("Y".to_string(), 30.77));
+        assert_eq!(rounded.budgets, outcome.budgets);
+        assert_eq!(outcome.winners[0].1, 79.23076923076923);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_report_rounds_to_the_requested_precision() {
+        let a = seq_phragmen(sample(), 2);
+        let report = a.json_report_rounded(2);
+        assert_eq!(report["winners"][0]["support"], 79.23);
+        assert_eq!(report["winners"][1]["support"], 30.77);
+        assert_eq!(a.json_report()["winners"][0]["support"], 79.23076923076923);
+    }
+
+    #[test]
+    fn excluding_a_winner_changes_the_outcome_but_an_idle_loser_does_not() {
+        let mut votelist = sample();
+        votelist.push(("D".to_string(), 1.0, vec!["W".to_string()]));
+        let impact = candidate_impact(votelist, 2);
+        assert_eq!(impact.len(), 4);
+        assert_eq!(impact["W"], ["Y", "Z"]);
+        assert_eq!(impact["X"], ["Y", "Z"]);
+        assert_eq!(impact["Z"], ["X", "Y"]);
+        assert_ne!(impact["Y"], ["Y", "Z"]);
+    }
+
+    #[test]
+    fn candidates_with_the_same_backers_are_fully_similar() {
+        let mut votelist = sample();
+        for (_, _, approvals) in votelist.iter_mut() {
+            if approvals.contains(&"X".to_string()) {
+                approvals.push("X2".to_string());
+            }
+        }
+        let a = seq_phragmen(votelist, 2);
+        assert_eq!(
+            a.similar_candidates(0.9),
+            [("X".to_string(), "X2".to_string(), 1.0)]
+        );
+        // X and Y share only A out of A, B and C.
+        let pairs = a.similar_candidates(0.3);
+        assert!(pairs.contains(&("X".to_string(), "Y".to_string(), 1.0 / 3.0)));
+        assert!(a.similar_candidates(1.0).is_empty());
+    }
+
+    #[test]
+    fn nan_scored_candidate_is_never_elected() {
+        let options = PhragmenOptions {
+            score_bias: HashMap::from([("Z".to_string(), f64::NAN)]),
+            ..Default::default()
+        };
+        let a = seq_phragmen_with(sample(), 2, &options);
+        let mut winners = winner_ids(&a);
+        winners.sort();
+        assert_eq!(winners, ["X", "Y"]);
+        assert!(a.voter_loads().iter().all(|load| load.is_finite()));
+    }
+
+    #[test]
+    fn flip_report_finds_the_small_budget_change_in_a_near_tie() {
+        let votelist = vec![
+            ("A".to_string(), 10.0, vec!["X".to_string()]),
+            ("B".to_string(), 9.9, vec!["Y".to_string()]),
+        ];
+        let report = seq_phragmen(votelist, 1).flip_report();
+        let (voterid, delta) = report.budget_change.unwrap();
+        // A losing 0.1 or B gaining 0.1 ties the seat.
+        assert!(voterid == "A" || voterid == "B");
+        assert!((delta.abs() - 0.1).abs() < 1e-4);
+        assert!(report.exclusions.is_empty());
+        assert!(report.last_seat_tie.is_empty());
+    }
+
+    #[test]
+    fn voter_rankings_break_a_tie_toward_the_preferred_candidate() {
+        let both = || vec!["X".to_string(), "Y".to_string()];
+        let votelist = vec![
+            ("A".to_string(), 10.0, both()),
+            ("B".to_string(), 5.0, both()),
+            ("C".to_string(), 3.0, both()),
+        ];
+        assert_eq!(winner_ids(&seq_phragmen(votelist.clone(), 1)), ["X"]);
+
+        let rank = |canids: &[&str]| canids.iter().map(|canid| canid.to_string()).collect();
+        let options = PhragmenOptions {
+            tie_break: TieBreak::VoterPreference,
+            // B's first choice W is not approved, so its budget goes to X.
+            voter_rankings: HashMap::from([
+                ("A".to_string(), rank(&["Y", "X"])),
+                ("B".to_string(), rank(&["W", "X"])),
+            ]),
+            ..Default::default()
+        };
+        let a = seq_phragmen_with(votelist.clone(), 1, &options);
+        assert_eq!(winner_ids(&a), ["Y"]);
+
+        // Without rankings the tie falls back to the lowest index.
+        let options = PhragmenOptions {
+            tie_break: TieBreak::VoterPreference,
+            ..Default::default()
+        };
+        assert_eq!(winner_ids(&seq_phragmen_with(votelist, 1, &options)), ["X"]);
+    }
+
+    #[test]
+    fn memory_estimate_is_linear_in_edges_and_near_the_allocation() {
+        let per_edge = estimate_memory(0, 0, 1);
+        assert!(per_edge > 0);
+        for edges in [0, 1_000, 100_000] {
+            assert_eq!(
+                estimate_memory(50, 10, edges),
+                estimate_memory(50, 10, 0) + edges * per_edge
+            );
+        }
+
+        use std::mem::size_of;
+        // 200 voters approving 5 of 30 candidates each.
+        let votelist: VoteList = (0..200)
+            .map(|voter| {
+                let approvals = (0..5).map(|k| format!("c{}", (voter + k) % 30)).collect();
+                (format!("v{voter}"), 1.0, approvals)
+            })
+            .collect();
+        let a = seq_phragmen(votelist, 5);
+        let floats = |v: &Vec<f64>| v.capacity() * size_of::<f64>();
+        let allocated = a.voterlist.capacity() * size_of::<Voter>()
+            + a.voterlist
+                .iter()
+                .map(|voter| voter.edges.capacity() * size_of::<Edge>())
+                .sum::<usize>()
+            + a.candidates.capacity() * size_of::<Candidate>()
+            + a.edgelist.capacity() * size_of::<Edge>()
+            + [
+                &a.voterload,
+                &a.edgeload,
+                &a.edgeweight,
+                &a.cansupport,
+                &a.canapproval,
+                &a.canscore,
+            ]
+            .into_iter()
+            .map(floats)
+            .sum::<usize>();
+        let estimate = estimate_memory(200, 30, 1_000);
+        assert!(estimate >= allocated / 2 && estimate <= allocated * 2);
+    }
+
+    #[test]
+    fn reset_and_rerun_matches_a_fresh_build() {
+        let options = PhragmenOptions::default();
+        let mut a = seq_phragmen(sample(), 2);
+        let capacity = a.edgeload.capacity();
+
+        let budgets = [30.0, 20.0, 30.0, 5.0];
+        let mut changed = sample();
+        for (ballot, &budget) in changed.iter_mut().zip(&budgets) {
+            ballot.1 = budget;
+        }
+        a.set_budgets(&budgets);
+        a.reset();
+        assert!(a.electedcandidates.is_empty() && a.electionorder.is_empty());
+        assert!(a.voterload.iter().all(|&load| load == 0.0));
+        assert_eq!(a.edgeload.capacity(), capacity);
+        while a.electedcandidates.len() < 2 && a.elect_next(&options).is_some() {}
+        a.loadstoweights_eps(options.eps);
+
+        let fresh = seq_phragmen(changed, 2);
+        assert_eq!(a.outcome(), fresh.outcome());
+        assert_ne!(winner_ids(&fresh), ["Z", "Y"]);
+    }
+
+    #[test]
+    fn ascii_chart_gives_the_top_winner_the_full_bar() {
+        let a = seq_phragmen(sample(), 3);
+        let chart = a.ascii_chart(40);
+        let lines: Vec<&str> = chart.lines().collect();
+        assert_eq!(lines.len(), 3);
+        for canid in ["X", "Y", "Z"] {
+            assert!(lines.iter().any(|line| line.starts_with(canid)));
+        }
+        let bar = |line: &str| line.matches('#').count();
+        let longest = lines.iter().max_by_key(|line| bar(line)).unwrap();
+        let (top, support) = &a.winners_by_support()[0];
+        assert!(longest.starts_with(top.as_str()));
+        assert_eq!(bar(longest), 40);
+        assert!(longest.ends_with(&format!("{support:.2}")));
+        assert!(lines.iter().all(|line| bar(line) <= 40));
+    }
+
+    #[test]
+    fn local_search_swaps_in_a_better_winner() {
+        // Start from the weak winner set {X, Y}, leaving C's 50 on Z unused.
+        let (voters, candidates) = setuplists(sample());
+        let mut a = Assignment::new(voters, candidates, None);
+        for canid in ["X", "Y"] {
+            let candidate = a.candidate_by_id(canid).unwrap().clone();
+            a.elect(&candidate);
+        }
+        let mut start = a.clone();
+        start.balance_winners();
+        let before = start.election_score().0;
+
+        assert_eq!(a.local_search(5), 1);
+        let after = a.election_score().0;
+        assert!(after > before + 1.0);
+        assert!(a.is_elected(a.candidate_by_id("Z").unwrap().index));
+        // The swapped set is locally optimal.
+        assert_eq!(a.local_search(5), 0);
+        assert!((a.election_score().0 - after).abs() < 1e-9);
+    }
+
+    #[test]
+    fn winner_voting_power_sums_to_the_council_size() {
+        let a = seq_phragmen(sample(), 3);
+        for council_size in [1.0, 3.0, 7.5] {
+            let powers = a.winner_voting_power(council_size);
+            assert_eq!(powers.len(), 3);
+            let total: f64 = powers.iter().map(|(_, power)| power).sum();
+            assert!((total - council_size).abs() < 1e-9);
+            // Power follows support.
+            for (canid, power) in &powers {
+                let index = a.candidate_by_id(canid).unwrap().index;
+                let share = a.cansupport[index] / a.election_score().1;
+                assert!((power - council_size * share).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn ballots_and_tuples_give_identical_results() {
+        let ballots: Vec<Ballot> = sample()
+            .into_iter()
+            .map(|(voterid, budget, approvals)| Ballot::new(voterid, budget, approvals))
+            .collect();
+        assert_eq!(
+            seq_phragmen(ballots.clone(), 2).outcome(),
+            seq_phragmen(sample(), 2).outcome()
+        );
+
+        let counted = Ballot::from_approval_counts("C", [("Y", 12.0), ("Z", 18.0)]);
+        assert_eq!(counted, Ballot::from(sample().swap_remove(2)));
+        let tuple: (String, f64, Vec<String>) = counted.into();
+        assert_eq!(tuple, sample()[2]);
+    }
+
+    #[test]
+    fn by_value_entry_points_take_ballots() {
+        let ballots: Vec<Ballot> = sample().into_iter().map(Ballot::from).collect();
+        assert_eq!(candidate_impact(ballots, 2), candidate_impact(sample(), 2));
+    }
+
+    #[test]
+    fn edgeless_voters_are_kept_or_dropped_without_moving_approval() {
+        let mut votelist = sample();
+        votelist.insert(1, ("D".to_string(), 5.0, vec![]));
+        let dropping = PhragmenOptions {
+            drop_edgeless_voters: true,
+            ..Default::default()
+        };
+        let kept = seq_phragmen_with(votelist.clone(), 2, &PhragmenOptions::default());
+        let dropped = seq_phragmen_with(votelist, 2, &dropping);
+
+        assert_eq!(kept.voterlist.len(), 5);
+        assert!(kept.dropped_voters().is_empty());
+        // The kept voter stays at zero load, an abstention.
+        assert_eq!(kept.voterlist[1].voterid, "D");
+        assert_eq!(kept.voter_loads()[1], 0.0);
+
+        assert_eq!(dropped.voterlist.len(), 4);
+        assert_eq!(dropped.dropped_voters(), ["D"]);
+        assert!(dropped.voterlist.iter().all(|voter| voter.voterid != "D"));
+
+        assert_eq!(kept.approvals(), dropped.approvals());
+        assert_eq!(kept.outcome().winners, dropped.outcome().winners);
+        assert_eq!(dropped.outcome(), seq_phragmen(sample(), 2).outcome());
+    }
+
+    #[test]
+    fn cohesive_third_of_the_budget_guarantees_one_of_three_seats() {
+        let ids = |canids: &[&str]| canids.iter().map(|canid| canid.to_string()).collect();
+        let votelist = vec![
+            ("A".to_string(), 10.0, ids(&["X", "Y"])),
+            ("B".to_string(), 10.0, ids(&["X"])),
+            ("C".to_string(), 40.0, ids(&["Z", "W", "V"])),
+        ];
+        let faction = ["A".to_string(), "B".to_string()];
+        assert_eq!(guaranteed_seats(&votelist, 3, &faction), 1);
+        let a = seq_phragmen(votelist.clone(), 3);
+        assert!(winner_ids(&a).contains(&"X".to_string()));
+
+        // Two thirds of the budget is two seats, unless the faction shares
+        // fewer candidates than that.
+        assert_eq!(guaranteed_seats(&votelist, 3, &["C".to_string()]), 2);
+        let everyone = ["A".to_string(), "B".to_string(), "C".to_string()];
+        assert_eq!(guaranteed_seats(&votelist, 3, &everyone), 0);
+        assert_eq!(guaranteed_seats(&votelist, 3, &["E".to_string()]), 0);
+    }
+
+    #[test]
+    fn soa_columns_rebuild_the_outcome() {
+        let outcome = seq_phragmen(sample(), 2).outcome();
+        let soa = outcome.to_soa();
+        let slice = |ids: &str, offsets: &[usize], index: usize| {
+            ids[offsets[index]..offsets[index + 1]].to_string()
+        };
+
+        let winners: Vec<(String, f64)> = (0..soa.winner_supports.len())
+            .map(|index| {
+                let canid = slice(&soa.winner_ids, &soa.winner_offsets, index);
+                (canid, soa.winner_supports[index])
+            })
+            .collect();
+        assert_eq!(winners, outcome.winners);
+        assert_eq!(soa.winner_ids, "ZY");
+
+        assert_eq!(soa.voter_budgets, outcome.budgets);
+        let mut assignments: Vec<(String, Vec<(String, f64)>)> = (0..soa.voter_budgets.len())
+            .map(|index| (slice(&soa.voter_ids, &soa.voter_offsets, index), Vec::new()))
+            .collect();
+        for row in 0..soa.assignment_weights.len() {
+            let canid = winners[soa.assignment_winners[row]].0.clone();
+            assignments[soa.assignment_voters[row]]
+                .1
+                .push((canid, soa.assignment_weights[row]));
+        }
+        assert_eq!(assignments, outcome.assignments);
+    }
+
+    #[test]
+    fn graded_final_scores_use_the_grade_fractions() {
+        let votelist = vec![
+            (
+                "A".to_string(),
+                10.0,
+                vec![
+                    ("X".to_string(), Grade::Strong),
+                    ("Y".to_string(), Grade::Weak),
+                ],
+            ),
+            (
+                "B".to_string(),
+                20.0,
+                vec![
+                    ("X".to_string(), Grade::Strong),
+                    ("Z".to_string(), Grade::Strong),
+                ],
+            ),
+            (
+                "C".to_string(),
+                30.0,
+                vec![
+                    ("Y".to_string(), Grade::Weak),
+                    ("Z".to_string(), Grade::Strong),
+                ],
+            ),
+        ];
+        let a = seq_phragmen_graded(votelist, 2);
+        let y = a.candidate_by_id("Y").unwrap().index;
+        assert!(!a.is_elected(y));
+        let mut rescored = a.clone();
+        rescored.recompute_scores(&PhragmenOptions::default());
+        let (_, score) = a
+            .final_scores()
+            .into_iter()
+            .find(|(canid, _)| canid == "Y")
+            .unwrap();
+        assert_eq!(score, rescored.canscore[y]);
+        assert!(a.complaint("Y").is_none());
+    }
+
+    #[test]
+    fn strong_grades_match_plain_ballots_and_weak_ones_shift_support() {
+        let graded = |grade_y: Grade| -> GradedVoteList {
+            sample()
+                .into_iter()
+                .map(|(voterid, budget, approvals)| {
+                    let graded = approvals
+                        .into_iter()
+                        .map(|canid| {
+                            let grade = if canid == "Y" { grade_y } else { Grade::Strong };
+                            (canid, grade)
+                        })
+                        .collect();
+                    (voterid, budget, graded)
+                })
+                .collect()
+        };
+        let plain = seq_phragmen(sample(), 3);
+        let strong = seq_phragmen_graded(graded(Grade::Strong), 3);
+        assert_eq!(strong.outcome(), plain.outcome());
+        assert_eq!(strong.approvals(), plain.approvals());
+
+        // Weak approvals of Y count half towards its approval, and support
+        // moves to the strongly graded X and Z.
+        let weak = seq_phragmen_graded(graded(Grade::Weak), 3);
+        let y = weak.candidate_by_id("Y").unwrap().index;
+        assert_eq!(weak.approvals()[y], 20.0);
+        let support =
+            |a: &Assignment, canid: &str| a.cansupport[a.candidate_by_id(canid).unwrap().index];
+        assert!(support(&weak, "Y") < support(&strong, "Y"));
+        assert!(
+            support(&weak, "X") + support(&weak, "Z")
+                > support(&strong, "X") + support(&strong, "Z")
+        );
+    }
+}